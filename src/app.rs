@@ -1,4 +1,9 @@
-use crate::{series::Series, term::Size, user::create_input::CreateInput, Mode};
+use crate::{
+    series::{Query, Series, WatchStatus},
+    term::Size,
+    user::create_input::CreateInput,
+    Mode,
+};
 use std::sync::Arc;
 
 pub(crate) struct App {
@@ -13,6 +18,8 @@ pub(crate) struct App {
 
     pub(crate) data: Data,
     pub(crate) create_data: Vec<CreateInput>,
+    pub(crate) hovered_field_idx: usize,
+    pub(crate) edited_series: Option<Series>,
 
     pub(crate) term_size: Size,
     pub(crate) mode: Mode,
@@ -21,14 +28,19 @@ pub(crate) struct App {
 }
 
 pub(crate) struct Data {
+    /// Position of the cursor within `available_series`, the page on screen.
+    /// Move it with the methods below, they keep `skip` following it.
     pub(crate) hovered_series_idx: usize,
     pub(crate) available_series: Vec<Series>,
     pub(crate) ignore_cached_series: bool,
+    pub(crate) last_deleted: Option<Series>,
+    pub(crate) query: Query,
 
-    take_backup: usize,
+    /// Page size and offset of `available_series` in the filtered library, the size follows the terminal height
     pub(crate) take: usize,
     pub(crate) skip: usize,
     pub(crate) total_series: usize,
+    pub(crate) status_counts: Vec<(WatchStatus, usize)>,
 }
 
 impl Data {
@@ -37,19 +49,86 @@ impl Data {
             hovered_series_idx: 0,
             available_series: vec![],
             ignore_cached_series: true,
-            take_backup: 16,
+            last_deleted: None,
+            query: Query::default(),
             take: 16,
             skip: 0,
             total_series,
+            status_counts: vec![],
         }
     }
 
+    /// Changes the page size, keeping the cursor on screen
     pub(crate) fn set_take(&mut self, take: usize) {
-        self.take_backup = self.take;
+        if take == self.take {
+            return;
+        }
+
         self.take = take;
+        self.ignore_cached_series = true;
+        self.clamp_cursor();
+    }
+
+    /// Where the cursor is in the whole filtered library
+    pub(crate) fn cursor(&self) -> usize {
+        self.skip + self.hovered_series_idx
+    }
+
+    pub(crate) fn half_page(&self) -> usize {
+        (self.take / 2).max(1)
     }
 
-    pub(crate) fn restore_take(&mut self) {
-        self.take = self.take_backup;
+    /// Moves the cursor by `offset` series, stopping at either end
+    pub(crate) fn move_by(&mut self, offset: isize) {
+        self.jump_to(self.cursor().saturating_add_signed(offset));
+    }
+
+    /// Moves the page by `offset` series, dragging the cursor along only when it would go off screen
+    pub(crate) fn scroll_by(&mut self, offset: isize) {
+        let cursor = self.cursor();
+        let max_skip = self.total_series.saturating_sub(self.take);
+        let skip = self.skip.saturating_add_signed(offset).min(max_skip);
+
+        if skip != self.skip {
+            self.skip = skip;
+            self.ignore_cached_series = true;
+        }
+
+        self.jump_to(cursor.clamp(skip, skip + self.take.saturating_sub(1)));
+    }
+
+    /// Back to the first series, for when the list is filtered or sorted differently
+    pub(crate) fn reset_cursor(&mut self) {
+        self.skip = 0;
+        self.hovered_series_idx = 0;
+        self.ignore_cached_series = true;
+    }
+
+    /// Moves the cursor to a position in the whole list, scrolling the page to it when needed
+    pub(crate) fn jump_to(&mut self, position: usize) {
+        let position = position.min(self.total_series.saturating_sub(1));
+
+        if position < self.skip {
+            self.skip = position;
+            self.ignore_cached_series = true;
+        } else if position >= self.skip + self.take {
+            self.skip = position + 1 - self.take;
+            self.ignore_cached_series = true;
+        }
+
+        self.hovered_series_idx = position - self.skip;
+    }
+
+    /// Keeps the page and the cursor inside the list after it shrank
+    pub(crate) fn clamp_cursor(&mut self) {
+        let cursor = self.cursor();
+
+        let max_skip = self.total_series.saturating_sub(self.take);
+        if self.skip > max_skip {
+            self.skip = max_skip;
+            self.ignore_cached_series = true;
+        }
+
+        self.jump_to(cursor);
     }
 }
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use crate::term::color::ColorMode;
+
+pub(crate) struct Args {
+    pub(crate) db: Option<PathBuf>,
+    pub(crate) json: bool,
+    pub(crate) color: ColorMode,
+    pub(crate) command: Option<String>,
+    /// Everything after the command, left for the command to make sense of
+    pub(crate) rest: Vec<String>,
+}
+
+pub(crate) fn parse(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        db: None,
+        json: false,
+        color: ColorMode::Auto,
+        command: None,
+        rest: vec![],
+    };
+
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        if let Some(path) = arg.strip_prefix("--db=") {
+            parsed.db = Some(PathBuf::from(path));
+            continue;
+        }
+
+        if let Some(mode) = arg.strip_prefix("--color=") {
+            parsed.color = parse_color(mode)?;
+            continue;
+        }
+
+        match arg.as_str() {
+            "--db" => match args.next() {
+                Some(path) => parsed.db = Some(PathBuf::from(path)),
+                None => return Err("--db expects a path".to_string()),
+            },
+            "--json" => parsed.json = true,
+            "--color" => match args.next() {
+                Some(mode) => parsed.color = parse_color(&mode)?,
+                None => return Err("--color expects auto, always or never".to_string()),
+            },
+            "--help" | "-h" if parsed.command.is_none() => {
+                parsed.command = Some("help".to_string())
+            }
+            _ if parsed.command.is_some() => parsed.rest.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{arg}'")),
+            _ => parsed.command = Some(arg),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_color(mode: &str) -> Result<ColorMode, String> {
+    ColorMode::parse(mode)
+        .ok_or_else(|| format!("unknown color mode '{mode}', expected auto, always or never"))
+}
@@ -0,0 +1,727 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::{
+    db,
+    history::WatchEvent,
+    mal,
+    season::{self, Season},
+    series::{parse_tags, Query, Series, TagMatch, WatchStatus, RATING_RANGE},
+    transfer::{self, Format, TransferError},
+};
+
+pub(crate) const EXIT_OK: i32 = 0;
+pub(crate) const EXIT_ERROR: i32 = 1;
+pub(crate) const EXIT_USAGE: i32 = 2;
+pub(crate) const EXIT_NOT_FOUND: i32 = 3;
+pub(crate) const EXIT_AMBIGUOUS: i32 = 4;
+pub(crate) const EXIT_DUPLICATE: i32 = 5;
+
+const USAGE: &str = r"Usage: cli_series [--db PATH] [--json] [--color auto|always|never] [COMMAND]
+
+Without a command the interactive view opens. Its colors follow $COLORTERM and $TERM,
+and are left out when $NO_COLOR is set or stdout is not a terminal, unless --color=always.
+
+Commands:
+  info                                  Show which database is in use
+  list [--status S] [--search TEXT] [--tags A,B [--any]]
+                                        List series, with every tag or --any of them
+  show <name|guid>                      Show everything about one series
+  add --name NAME [--total N] [--current N] [--status S] [--rating 1-10] [--notes TEXT]
+      [--tags A,B] [--seasons 12/12,5/24] [--force]
+                                        Add a series, --force even when the name looks
+                                        like one already in the library
+  watch <name|guid> [+N|-N]             Mark episodes as watched, one by default
+  set <name|guid> [--name NAME] [--total N] [--current N] [--status S]
+      [--finished yes|no] [--airing-finished yes|no] [--rating 1-10] [--notes TEXT]
+      [--tags A,B] [--seasons 12/12,5/24]
+                                        Change fields of a series, --seasons '' makes it
+                                        single-season again
+  rm <name|guid>                        Delete a series
+  strict [on|off]                       Show or change whether the database itself refuses
+                                        names differing only in case or outer spaces
+  export [FILE] [--format json|csv]     Write every series to FILE, or stdout
+  import <FILE> [--format json|csv] [--merge] [--dry-run]
+                                        Read series from FILE, or stdin when FILE is -,
+                                        invalid rows are skipped and listed
+                                        --merge updates series with the same guid or name
+                                        --dry-run only lists what would change
+  import-mal <FILE> [--dry-run]         Add the series from a MyAnimeList XML export,
+                                        skipping names already in the library
+
+Statuses: watching, completed, dropped, plan_to_watch
+
+Series are picked by their guid, the start of it, or their whole name in any case.
+Only show also takes part of the name.
+
+Exit codes: 0 ok, 1 error, 2 bad usage, 3 no such series, 4 more than one series matched,
+5 the series looks like a duplicate";
+
+pub(crate) const USAGE_HINT: &str = "Run 'cli_series help' for usage";
+
+enum CliError {
+    Usage(String),
+    NotFound(String),
+    Ambiguous(String, Vec<Series>),
+    Duplicate(String, Vec<Series>),
+    Db(rusqlite::Error),
+    Transfer(TransferError),
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::NotFound(_) => EXIT_NOT_FOUND,
+            CliError::Ambiguous(_, _) => EXIT_AMBIGUOUS,
+            CliError::Duplicate(_, _) => EXIT_DUPLICATE,
+            CliError::Db(_) | CliError::Transfer(_) => EXIT_ERROR,
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{msg}\n{USAGE_HINT}"),
+            CliError::NotFound(needle) => write!(f, "no series matches '{needle}'"),
+            CliError::Ambiguous(needle, candidates) => {
+                write!(f, "'{needle}' matches more than one series:")?;
+                for series in candidates {
+                    write!(f, "\n  {}  {}", series.guid, series.name)?;
+                }
+
+                Ok(())
+            }
+            CliError::Duplicate(name, existing) => {
+                write!(f, "'{name}' looks like a series already in the library:")?;
+                for series in existing {
+                    write!(f, "\n  {}  {}", series.guid, series.name)?;
+                }
+
+                write!(f, "\nUse --force to add it anyway")
+            }
+            CliError::Db(er) => write!(f, "{er}"),
+            CliError::Transfer(er) => write!(f, "{er}"),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for CliError {
+    fn from(er: rusqlite::Error) -> Self {
+        CliError::Db(er)
+    }
+}
+
+impl From<TransferError> for CliError {
+    fn from(er: TransferError) -> Self {
+        CliError::Transfer(er)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(er: io::Error) -> Self {
+        CliError::Transfer(TransferError::Io(er))
+    }
+}
+
+/// `--key value` and `--key=value` pairs, `--flag`s, plus whatever was not an option
+struct Options {
+    positional: Vec<String>,
+    values: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String], known: &[&str], known_flags: &[&str]) -> Result<Self, CliError> {
+        let mut options = Options {
+            positional: vec![],
+            values: vec![],
+            flags: vec![],
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                options.positional.push(arg.clone());
+                continue;
+            };
+
+            if known_flags.contains(&option) {
+                options.flags.push(option.to_string());
+                continue;
+            }
+
+            let (key, value) = match option.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => match args.next() {
+                    Some(value) => (option.to_string(), value.clone()),
+                    None => return Err(CliError::Usage(format!("--{option} expects a value"))),
+                },
+            };
+
+            if !known.contains(&key.as_str()) {
+                return Err(CliError::Usage(format!("unknown option '--{key}'")));
+            }
+
+            options.values.push((key, value));
+        }
+
+        Ok(options)
+    }
+
+    fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|known| known == flag)
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(known, _)| known == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn get_i32(&self, key: &str) -> Result<Option<i32>, CliError> {
+        self.get(key)
+            .map(|value| {
+                value
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|nr| *nr >= 0)
+                    .ok_or_else(|| CliError::Usage(format!("--{key} expects a positive number")))
+            })
+            .transpose()
+    }
+
+    fn get_bool(&self, key: &str) -> Result<Option<bool>, CliError> {
+        self.get(key)
+            .map(|value| match value.to_ascii_lowercase().as_str() {
+                "yes" | "y" | "true" => Ok(true),
+                "no" | "n" | "false" => Ok(false),
+                _ => Err(CliError::Usage(format!("--{key} expects yes or no"))),
+            })
+            .transpose()
+    }
+
+    fn get_rating(&self) -> Result<Option<u8>, CliError> {
+        self.get("rating")
+            .map(|value| {
+                value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|rating| RATING_RANGE.contains(rating))
+                    .ok_or_else(|| {
+                        CliError::Usage("--rating expects a number from 1 to 10".to_string())
+                    })
+            })
+            .transpose()
+    }
+
+    fn get_seasons(&self) -> Result<Option<Vec<Season>>, CliError> {
+        self.get("seasons")
+            .map(|value| season::parse(value).map_err(CliError::Usage))
+            .transpose()
+    }
+
+    fn get_status(&self) -> Result<Option<WatchStatus>, CliError> {
+        self.get("status")
+            .map(|value| {
+                WatchStatus::parse(value)
+                    .ok_or_else(|| CliError::Usage(format!("unknown status '{value}'")))
+            })
+            .transpose()
+    }
+
+    /// `--format` when given, otherwise guessed from the file extension, JSON when neither helps
+    fn get_format(&self, path: Option<&str>) -> Result<Format, CliError> {
+        if let Some(format) = self.get("format") {
+            return Format::parse(format)
+                .ok_or_else(|| CliError::Usage(format!("unknown format '{format}'")));
+        }
+
+        Ok(path
+            .and_then(|path| Format::from_path(Path::new(path)))
+            .unwrap_or(Format::Json))
+    }
+
+    /// The one positional argument naming the series to work on
+    fn needle(&self) -> Result<&str, CliError> {
+        match self.positional.first() {
+            Some(needle) => Ok(needle),
+            None => Err(CliError::Usage(
+                "expected a series name or guid".to_string(),
+            )),
+        }
+    }
+}
+
+/// Runs a non-interactive command, returning the process exit code
+pub(crate) fn run(command: &str, args: &[String], json: bool) -> i32 {
+    let result = match command {
+        "info" => info(json),
+        "list" => list(args, json),
+        "show" => show(args, json),
+        "add" => add(args, json),
+        "watch" => watch(args, json),
+        "set" => set(args, json),
+        "rm" => rm(args, json),
+        "strict" => strict(args, json),
+        "export" => export(args),
+        "import" => import(args, json),
+        "import-mal" => import_mal(args, json),
+        "help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => Err(CliError::Usage(format!("unknown command '{command}'"))),
+    };
+
+    match result {
+        Ok(_) => EXIT_OK,
+        Err(er) => {
+            eprintln!("{er}");
+            er.exit_code()
+        }
+    }
+}
+
+fn info(json: bool) -> Result<(), CliError> {
+    let conn = db::get_connection()?;
+    let path = db::path().display().to_string();
+    let schema = db::schema_version(&conn)?;
+    let series = Series::count_total()?;
+    let strict_names = db::strict_names(&conn)?;
+
+    if json {
+        #[derive(Serialize)]
+        struct Info {
+            database: String,
+            schema: usize,
+            series: usize,
+            strict_names: bool,
+        }
+
+        print_json(&Info {
+            database: path,
+            schema,
+            series,
+            strict_names,
+        });
+        return Ok(());
+    }
+
+    println!("Database: {path}");
+    println!("Schema:   v{schema}");
+    println!("Series:   {series}");
+    println!("Strict:   {}", if strict_names { "on" } else { "off" });
+
+    Ok(())
+}
+
+fn list(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(args, &["status", "search", "tags"], &["any"])?;
+    let query = Query {
+        search: options.get("search").map(str::to_string),
+        status: options.get_status()?,
+        tags: parse_tags(options.get("tags").unwrap_or_default()),
+        tag_match: match options.has_flag("any") {
+            true => TagMatch::Any,
+            false => TagMatch::All,
+        },
+        ..Query::default()
+    };
+
+    let total = Series::count(&query)?;
+    let series = Series::get(total, 0, &query)?;
+
+    if json {
+        print_json(&series);
+        return Ok(());
+    }
+
+    for series in series {
+        println!(
+            "{}\t{}\t{}/{}\t{}",
+            series.guid,
+            series.name,
+            series.current_episode,
+            series.total_episodes,
+            series.status.as_str()
+        );
+    }
+
+    Ok(())
+}
+
+fn show(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(args, &[], &[])?;
+    let series = find_one(options.needle()?, true)?;
+
+    print_series(&series, json);
+    Ok(())
+}
+
+fn add(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(
+        args,
+        &[
+            "name", "total", "current", "status", "rating", "notes", "tags", "seasons",
+        ],
+        &["force"],
+    )?;
+
+    let Some(name) = options.get("name").filter(|name| !name.trim().is_empty()) else {
+        return Err(CliError::Usage("add needs --name".to_string()));
+    };
+
+    let seasons = options.get_seasons()?.unwrap_or_default();
+    let (current, total) = season::counts(
+        &seasons,
+        options.get_i32("current")?,
+        options.get_i32("total")?,
+    );
+    if let (Some(total @ 1..), Some(current)) = (total, current) {
+        if current > total {
+            return Err(CliError::Usage(format!(
+                "--current {current} is past --total {total}"
+            )));
+        }
+    }
+
+    let mut series = Series::new(
+        name.trim().to_string(),
+        None,
+        None,
+        total,
+        current,
+        options.get_status()?,
+    );
+    series.rating = options.get_rating()?;
+    series.notes = options.get("notes").unwrap_or_default().to_string();
+    series.tags = parse_tags(options.get("tags").unwrap_or_default());
+    series.seasons = seasons;
+    let conn = db::get_connection()?;
+    match options.has_flag("force") {
+        true => {
+            series.insert(&conn)?;
+        }
+        false => {
+            let duplicates = series.try_insert(&conn)?;
+            if !duplicates.is_empty() {
+                return Err(CliError::Duplicate(series.name, duplicates));
+            }
+        }
+    }
+
+    print_series(&series, json);
+    Ok(())
+}
+
+fn watch(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(args, &[], &[])?;
+    let mut series = find_one(options.needle()?, false)?;
+
+    let qty = match options.positional.get(1) {
+        Some(qty) => qty
+            .strip_prefix('+')
+            .unwrap_or(qty)
+            .parse::<i32>()
+            .map_err(|_| CliError::Usage(format!("'{qty}' is not an episode count like +1")))?,
+        None => 1,
+    };
+
+    series.try_watch(&db::get_connection()?, qty)?;
+
+    print_series(&series, json);
+    Ok(())
+}
+
+fn set(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(
+        args,
+        &[
+            "name",
+            "total",
+            "current",
+            "status",
+            "finished",
+            "airing-finished",
+            "rating",
+            "notes",
+            "tags",
+            "seasons",
+        ],
+        &[],
+    )?;
+    let mut series = find_one(options.needle()?, false)?;
+
+    if let Some(name) = options.get("name") {
+        series.name = name.trim().to_string();
+    }
+    if let Some(status) = options.get_status()? {
+        series.status = status;
+    }
+    if let Some(is_finished) = options.get_bool("finished")? {
+        series.is_finished = is_finished;
+    }
+    if let Some(is_airing_finished) = options.get_bool("airing-finished")? {
+        series.is_airing_finished = is_airing_finished;
+    }
+    if let Some(rating) = options.get_rating()? {
+        series.rating = Some(rating);
+    }
+    if let Some(notes) = options.get("notes") {
+        series.notes = notes.to_string();
+    }
+    if let Some(tags) = options.get("tags") {
+        series.tags = parse_tags(tags);
+    }
+    if let Some(seasons) = options.get_seasons()? {
+        series.seasons = seasons;
+    }
+
+    let total = options.get_i32("total")?;
+    let current = options.get_i32("current")?;
+    // They would be overwritten by the season totals on save
+    if !series.seasons.is_empty() && (total.is_some() || current.is_some()) {
+        return Err(CliError::Usage(format!(
+            "{} has seasons, change them with --seasons instead of --current or --total",
+            series.name
+        )));
+    }
+    if let Some(total) = total {
+        series.total_episodes = total;
+    }
+    if let Some(current) = current {
+        series.current_episode = current;
+    }
+    // A blank --name, or a current episode past the total
+    series.check().map_err(CliError::Usage)?;
+
+    series.try_update(&db::get_connection()?)?;
+
+    print_series(&series, json);
+    Ok(())
+}
+
+fn rm(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(args, &[], &[])?;
+    let mut series = find_one(options.needle()?, false)?;
+
+    let conn = db::get_connection()?;
+    series.try_delete(&conn)?;
+    // There is no undo here
+    WatchEvent::forget(&conn, &series.guid)?;
+
+    match json {
+        true => print_json(&series),
+        false => println!("Deleted {} {}", series.guid, series.name),
+    }
+
+    Ok(())
+}
+
+fn strict(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(args, &[], &[])?;
+    let conn = db::get_connection()?;
+
+    match options.positional.first().map(String::as_str) {
+        Some("on") => db::set_strict_names(&conn, true)?,
+        Some("off") => db::set_strict_names(&conn, false)?,
+        Some(value) => {
+            return Err(CliError::Usage(format!(
+                "strict expects on or off, not '{value}'"
+            )))
+        }
+        None => (),
+    }
+
+    let strict = db::strict_names(&conn)?;
+    match json {
+        true => print_json(&strict),
+        false => println!("Strict names: {}", if strict { "on" } else { "off" }),
+    }
+
+    Ok(())
+}
+
+fn export(args: &[String]) -> Result<(), CliError> {
+    let options = Options::parse(args, &["format"], &[])?;
+    let path = options.positional.first().map(String::as_str);
+    let format = options.get_format(path)?;
+
+    let mut conn = db::get_connection()?;
+
+    match path {
+        Some(path) if path != "-" => {
+            let file = BufWriter::new(File::create(path)?);
+            let count = transfer::export(&mut conn, format, file)?;
+
+            eprintln!("Exported {count} series to {path}");
+        }
+        _ => {
+            transfer::export(&mut conn, format, io::stdout().lock())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn import(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(args, &["format"], &["merge", "dry-run"])?;
+    let Some(path) = options.positional.first() else {
+        return Err(CliError::Usage(
+            "import expects a file, or - for stdin".to_string(),
+        ));
+    };
+
+    let format = options.get_format(Some(path))?;
+    let merge = options.has_flag("merge");
+    let dry_run = options.has_flag("dry-run");
+
+    let mut conn = db::get_connection()?;
+    let changes = match path.as_str() {
+        "-" => transfer::import(&mut conn, format, io::stdin().lock(), merge, dry_run)?,
+        path => {
+            let file = BufReader::new(File::open(path)?);
+            transfer::import(&mut conn, format, file, merge, dry_run)?
+        }
+    };
+
+    if json {
+        print_json(&changes);
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("{change}");
+    }
+
+    let count = |action: fn(&transfer::Action) -> bool| {
+        changes
+            .iter()
+            .filter(|change| action(&change.action))
+            .count()
+    };
+    let inserts = count(|action| matches!(action, transfer::Action::Insert));
+    let skips = count(|action| matches!(action, transfer::Action::Skip));
+    let updates = changes.len() - inserts - skips;
+
+    match dry_run {
+        true => println!(
+            "Dry run: would insert {inserts} and update {updates} series, {skips} invalid skipped"
+        ),
+        false => {
+            println!("Inserted {inserts} and updated {updates} series, {skips} invalid skipped")
+        }
+    }
+
+    Ok(())
+}
+
+fn import_mal(args: &[String], json: bool) -> Result<(), CliError> {
+    let options = Options::parse(args, &[], &["dry-run"])?;
+    let Some(path) = options.positional.first() else {
+        return Err(CliError::Usage(
+            "import-mal expects an animelist.xml file".to_string(),
+        ));
+    };
+    let dry_run = options.has_flag("dry-run");
+
+    let entries = mal::parse(BufReader::new(File::open(path)?))?;
+    let mut conn = db::get_connection()?;
+    let summary = mal::import(&mut conn, entries, dry_run)?;
+
+    if json {
+        #[derive(Serialize)]
+        struct Summary<'a> {
+            inserted: &'a [String],
+            skipped: &'a [String],
+        }
+
+        print_json(&Summary {
+            inserted: &summary.inserted,
+            skipped: &summary.skipped,
+        });
+        return Ok(());
+    }
+
+    for name in &summary.inserted {
+        println!("insert  {name}");
+    }
+    for name in &summary.skipped {
+        println!("skip    {name} (already in the library)");
+    }
+
+    let (inserted, skipped) = (summary.inserted.len(), summary.skipped.len());
+    match dry_run {
+        true => println!("Dry run: would import {inserted} series, {skipped} duplicates skipped"),
+        false => println!("Imported {inserted} series, {skipped} duplicates skipped"),
+    }
+
+    Ok(())
+}
+
+/// Commands that change the series only take its guid, a guid prefix or its whole name,
+/// so a stray word can't pick one by accident
+fn find_one(needle: &str, by_part_of_name: bool) -> Result<Series, CliError> {
+    let mut candidates = Series::find(needle, by_part_of_name)?;
+
+    match candidates.len() {
+        0 => Err(CliError::NotFound(needle.to_string())),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(CliError::Ambiguous(needle.to_string(), candidates)),
+    }
+}
+
+fn print_series(series: &Series, json: bool) {
+    if json {
+        print_json(series);
+        return;
+    }
+
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+
+    println!("Guid:            {}", series.guid);
+    println!("Name:            {}", series.name);
+    println!("Status:          {}", series.status.label());
+    println!(
+        "Progress:        {}/{}",
+        series.current_episode, series.total_episodes
+    );
+    if !series.seasons.is_empty() {
+        println!("Seasons:         {}", season::format(&series.seasons));
+    }
+    if let Some(next) = series.next_episode() {
+        println!("Next:            {next}");
+    }
+    println!("Finished:        {}", yes_no(series.is_finished));
+    println!("Airing finished: {}", yes_no(series.is_airing_finished));
+
+    if let Some(rating) = series.rating {
+        println!("Rating:          {rating}/10");
+    }
+    if !series.tags.is_empty() {
+        println!("Tags:            {}", series.tags.join(", "));
+    }
+    if !series.notes.is_empty() {
+        println!(
+            "Notes:           {}",
+            series.notes.replace('\n', "\n                 ")
+        );
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(er) => eprintln!("{er}"),
+    }
+}
@@ -1,7 +1,286 @@
-use rusqlite::{Connection, Error};
+use rusqlite::{ffi, Connection, Error, OptionalExtension};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
-const DB_PATH: &str = "data.db";
+const DB_ENV: &str = "CLI_SERIES_DB";
+const DB_FILE: &str = "data.db";
+
+static DB_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+// Each entry moves the schema one version forward; the index + 1 is the version it produces.
+// Never edit an entry that already shipped, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    r"
+    CREATE TABLE IF NOT EXISTS series (
+        guid               TEXT    PRIMARY KEY NOT NULL,
+        name               TEXT    NOT NULL,
+        is_finished        INTEGER NOT NULL DEFAULT 0,
+        is_airing_finished INTEGER NOT NULL DEFAULT 0,
+        total_episodes     INTEGER NOT NULL DEFAULT 0,
+        current_episode    INTEGER NOT NULL DEFAULT 0
+    );
+    ",
+    // 2: sortable date added, and a place for preferences
+    r"
+    ALTER TABLE series ADD COLUMN added_at TEXT;
+    UPDATE series SET added_at = datetime('now');
+
+    CREATE TABLE settings (
+        key   TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );
+    ",
+    // 3: watch status, backfilled from the progress we already know about
+    r"
+    ALTER TABLE series ADD COLUMN status TEXT NOT NULL DEFAULT 'plan_to_watch';
+    UPDATE series SET status = 'completed' WHERE is_finished = 1;
+    UPDATE series SET status = 'watching' WHERE is_finished = 0 AND current_episode > 0;
+    ",
+    // 4: episode progress history. Rows outlive their series on purpose, so undoing a
+    // delete brings the history back with it.
+    r"
+    CREATE TABLE watch_events (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        series_guid TEXT    NOT NULL,
+        old_episode INTEGER NOT NULL,
+        new_episode INTEGER NOT NULL,
+        watched_at  TEXT    NOT NULL DEFAULT (datetime('now'))
+    );
+    CREATE INDEX watch_events_series ON watch_events (series_guid, id);
+    ",
+    // 5: last edit time, unknown for everything saved before it
+    r"
+    ALTER TABLE series ADD COLUMN updated_at TEXT;
+    ",
+    // 6: personal score from 1 to 10, and free-form notes
+    r"
+    ALTER TABLE series ADD COLUMN rating INTEGER;
+    ALTER TABLE series ADD COLUMN notes TEXT NOT NULL DEFAULT '';
+    ",
+    // 7: free-form tags, like genres or who recommended the series
+    r"
+    CREATE TABLE tags (
+        id   INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT    NOT NULL UNIQUE COLLATE NOCASE
+    );
+
+    CREATE TABLE series_tags (
+        series_guid TEXT    NOT NULL,
+        tag_id      INTEGER NOT NULL REFERENCES tags (id),
+        PRIMARY KEY (series_guid, tag_id)
+    );
+    CREATE INDEX series_tags_tag ON series_tags (tag_id);
+    ",
+    // 8: optional per-season progress, series without rows here stay single-season
+    r"
+    CREATE TABLE seasons (
+        series_guid     TEXT    NOT NULL,
+        number          INTEGER NOT NULL,
+        total_episodes  INTEGER NOT NULL DEFAULT 0,
+        current_episode INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (series_guid, number)
+    );
+    ",
+];
+
+/// Picks the database file, in order: the `--db` argument, `$CLI_SERIES_DB`,
+/// then `$XDG_DATA_HOME/cli_series/data.db` (or `~/.local/share/...` when unset).
+pub(crate) fn resolve_path(cli_path: Option<PathBuf>) -> PathBuf {
+    if let Some(path) = cli_path {
+        return path;
+    }
+
+    if let Some(path) = env::var_os(DB_ENV).filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    // The spec says relative values must be ignored
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+
+    match data_home {
+        Some(data_home) => data_home.join(env!("CARGO_PKG_NAME")).join(DB_FILE),
+        None => PathBuf::from(DB_FILE),
+    }
+}
+
+/// Fixes the database location for the rest of the run and creates its parent directories.
+pub(crate) fn init(cli_path: Option<PathBuf>) -> Result<&'static Path, io::Error> {
+    let path = DB_PATH.get_or_init(|| resolve_path(cli_path));
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(path)
+}
+
+pub(crate) fn path() -> &'static Path {
+    DB_PATH.get_or_init(|| resolve_path(None))
+}
 
 pub(crate) fn get_connection() -> Result<Connection, Error> {
-    Connection::open(DB_PATH)
+    let mut conn = Connection::open(path())?;
+    migrate(&mut conn)?;
+
+    Ok(conn)
+}
+
+pub(crate) fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>, Error> {
+    conn.query_row("SELECT value FROM settings WHERE key = ?", [key], |row| {
+        row.get(0)
+    })
+    .optional()
+}
+
+pub(crate) fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<(), Error> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = ?2",
+        [key, value],
+    )?;
+
+    Ok(())
+}
+
+const STRICT_NAMES_INDEX: &str = "series_name_unique";
+
+/// Whether the database refuses names that only differ in case or surrounding spaces
+pub(crate) fn strict_names(conn: &Connection) -> Result<bool, Error> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = ?)",
+        [STRICT_NAMES_INDEX],
+        |row| row.get(0),
+    )
+}
+
+/// Not a migration since it's opt-in, and turning it on fails while duplicates exist
+pub(crate) fn set_strict_names(conn: &Connection, strict: bool) -> Result<(), Error> {
+    let sql = match strict {
+        true => format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS {STRICT_NAMES_INDEX} ON series (lower(trim(name)))"
+        ),
+        false => format!("DROP INDEX IF EXISTS {STRICT_NAMES_INDEX}"),
+    };
+
+    conn.execute(&sql, [])?;
+    Ok(())
+}
+
+pub(crate) fn schema_version(conn: &Connection) -> Result<usize, Error> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+pub(crate) fn migrate(conn: &mut Connection) -> Result<(), Error> {
+    let version = schema_version(conn)?;
+
+    if version > MIGRATIONS.len() {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_CANTOPEN),
+            Some(format!(
+                "database schema is v{version} but this build only knows up to v{}, update cli_series",
+                MIGRATIONS.len()
+            )),
+        ));
+    }
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// An empty library in memory, with every migration applied
+#[cfg(test)]
+pub(crate) fn open_in_memory() -> Connection {
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrate(&mut conn).unwrap();
+
+    conn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap();
+        let names = stmt.query_map([], |row| row.get(0)).unwrap();
+
+        names.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn migrates_once() {
+        let mut conn = open_in_memory();
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len());
+
+        conn.execute(
+            "INSERT INTO series (guid, name) VALUES ('a', 'Frieren')",
+            [],
+        )
+        .unwrap();
+        let before = tables(&conn);
+
+        // Nothing left to run, and nothing run twice
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len());
+        assert_eq!(tables(&conn), before);
+        let count: usize = conn
+            .query_row("SELECT count(*) FROM series", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn upgrades_old_databases_in_place() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO series (guid, name, is_finished, current_episode) VALUES ('a', 'Frieren', 1, 28)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len());
+        let status: String = conn
+            .query_row("SELECT status FROM series WHERE guid = 'a'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(status, "completed");
+    }
+
+    #[test]
+    fn refuses_newer_databases() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+
+        let er = migrate(&mut conn).unwrap_err();
+
+        assert!(er.to_string().contains("update cli_series"), "{er}");
+        // Left exactly as it was
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len() + 1);
+        assert!(tables(&conn).is_empty());
+    }
 }
@@ -0,0 +1,258 @@
+use rusqlite::{Connection, Error, OptionalExtension, Row};
+
+use crate::{season, series::Series};
+
+/// One change of a series' episode progress
+#[derive(Debug, Clone)]
+pub(crate) struct WatchEvent {
+    pub(crate) id: i64,
+    pub(crate) old_episode: i32,
+    pub(crate) new_episode: i32,
+    /// UTC, as `YYYY-MM-DD HH:MM:SS`
+    pub(crate) watched_at: String,
+}
+
+const COLUMNS: &str = "id, old_episode, new_episode, watched_at";
+
+impl WatchEvent {
+    fn from_row(row: &Row) -> Result<Self, Error> {
+        Ok(Self {
+            id: row.get(0)?,
+            old_episode: row.get(1)?,
+            new_episode: row.get(2)?,
+            watched_at: row.get(3)?,
+        })
+    }
+
+    pub(crate) fn record(
+        conn: &Connection,
+        series_guid: &str,
+        old_episode: i32,
+        new_episode: i32,
+    ) -> Result<(), Error> {
+        conn.execute(
+            "INSERT INTO watch_events (series_guid, old_episode, new_episode) VALUES (?, ?, ?)",
+            (series_guid, old_episode, new_episode),
+        )?;
+
+        Ok(())
+    }
+
+    /// Newest first
+    pub(crate) fn for_series(conn: &Connection, series_guid: &str) -> Result<Vec<Self>, Error> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {COLUMNS} FROM watch_events WHERE series_guid = ? ORDER BY id DESC"
+        ))?;
+        let mut rows = stmt.query([series_guid])?;
+
+        let mut events = vec![];
+        while let Some(row) = rows.next()? {
+            events.push(Self::from_row(row)?);
+        }
+
+        Ok(events)
+    }
+
+    /// For series deleted for good, once the delete can no longer be undone
+    pub(crate) fn forget(conn: &Connection, series_guid: &str) -> Result<(), Error> {
+        conn.execute(
+            "DELETE FROM watch_events WHERE series_guid = ?",
+            [series_guid],
+        )?;
+
+        Ok(())
+    }
+
+    /// Events of series that are gone, left behind when the app quit before forgetting them
+    pub(crate) fn forget_orphans(conn: &Connection) -> Result<(), Error> {
+        conn.execute(
+            "DELETE FROM watch_events WHERE series_guid NOT IN (SELECT guid FROM series)",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Puts the progress back to before the newest event and forgets the event,
+    /// `None` when the series has no history
+    pub(crate) fn rollback_last(
+        conn: &mut Connection,
+        series_guid: &str,
+    ) -> Result<Option<Self>, Error> {
+        let tx = conn.transaction()?;
+
+        let last = tx
+            .query_row(
+                &format!(
+                    "SELECT {COLUMNS} FROM watch_events WHERE series_guid = ? ORDER BY id DESC LIMIT 1"
+                ),
+                [series_guid],
+                Self::from_row,
+            )
+            .optional()?;
+
+        let Some(last) = last else {
+            return Ok(None);
+        };
+
+        // Straight to the columns, only the progress changes so there's no need for `Series::try_update`.
+        // Dropping back below the total also undoes the auto-finish of `Series::add_episodes`.
+        tx.execute(
+            r"
+            UPDATE series
+            SET current_episode = ?1,
+                is_finished = CASE WHEN total_episodes > ?1 THEN 0 ELSE is_finished END,
+                status = CASE WHEN total_episodes > ?1 AND status = 'completed' THEN 'watching' ELSE status END,
+                updated_at = datetime('now')
+            WHERE guid = ?2
+            ",
+            (last.old_episode, series_guid),
+        )?;
+
+        // The seasons hold the progress of multi-season series, walk them back by the same amount
+        if let Some(mut series) =
+            Series::get_by_guid(&tx, series_guid)?.filter(|series| !series.seasons.is_empty())
+        {
+            season::add_episodes(&mut series.seasons, last.old_episode - last.new_episode);
+            season::save(&tx, series_guid, &series.seasons)?;
+        }
+
+        tx.execute("DELETE FROM watch_events WHERE id = ?", [last.id])?;
+        tx.commit()?;
+
+        Ok(Some(last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db,
+        season::Season,
+        series::{Series, WatchStatus},
+    };
+
+    fn stored(conn: &Connection, guid: &str) -> Series {
+        Series::get_by_guid(conn, guid).unwrap().unwrap()
+    }
+
+    #[test]
+    fn logs_watching_and_edits_but_not_other_changes() {
+        let conn = db::open_in_memory();
+        let mut series = Series::new("Frieren".to_string(), None, None, Some(28), Some(3), None);
+        series.insert(&conn).unwrap();
+
+        series.try_watch(&conn, 2).unwrap();
+        series.current_episode = 20;
+        series.try_update(&conn).unwrap();
+        series.notes = "Only the notes".to_string();
+        series.try_update(&conn).unwrap();
+        series.current_episode = 21;
+        series.try_update_quietly(&conn).unwrap();
+
+        let events = WatchEvent::for_series(&conn, &series.guid).unwrap();
+        let changes = events
+            .iter()
+            .map(|event| (event.old_episode, event.new_episode))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, vec![(5, 20), (3, 5)]);
+    }
+
+    #[test]
+    fn rolls_back_one_event_at_a_time() {
+        let mut conn = db::open_in_memory();
+        let mut series = Series::new(
+            "Frieren".to_string(),
+            None,
+            Some(true),
+            Some(28),
+            Some(3),
+            None,
+        );
+        series.insert(&conn).unwrap();
+        let guid = series.guid.clone();
+
+        series.try_watch(&conn, 2).unwrap();
+        series.try_watch(&conn, 23).unwrap();
+        assert!(stored(&conn, &guid).is_finished);
+
+        let undone = WatchEvent::rollback_last(&mut conn, &guid)
+            .unwrap()
+            .unwrap();
+        assert_eq!((undone.old_episode, undone.new_episode), (5, 28));
+
+        // Back below the total undoes the auto-finish
+        let series = stored(&conn, &guid);
+        assert_eq!(series.current_episode, 5);
+        assert!(!series.is_finished);
+        assert_eq!(series.status, WatchStatus::Watching);
+
+        WatchEvent::rollback_last(&mut conn, &guid)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored(&conn, &guid).current_episode, 3);
+
+        assert!(WatchEvent::rollback_last(&mut conn, &guid)
+            .unwrap()
+            .is_none());
+        assert_eq!(stored(&conn, &guid).current_episode, 3);
+    }
+
+    #[test]
+    fn rolls_back_through_the_seasons() {
+        let mut conn = db::open_in_memory();
+        let mut series = Series::new("Bocchi".to_string(), None, None, None, None, None);
+        series.seasons = vec![
+            Season {
+                total_episodes: 12,
+                current_episode: 10,
+            },
+            Season {
+                total_episodes: 12,
+                current_episode: 0,
+            },
+        ];
+        series.insert(&conn).unwrap();
+        let guid = series.guid.clone();
+
+        series.try_watch(&conn, 4).unwrap();
+        assert_eq!(series.seasons[1].current_episode, 2);
+
+        WatchEvent::rollback_last(&mut conn, &guid)
+            .unwrap()
+            .unwrap();
+
+        let series = stored(&conn, &guid);
+        assert_eq!(series.current_episode, 10);
+        assert_eq!(
+            series
+                .seasons
+                .iter()
+                .map(|season| season.current_episode)
+                .collect::<Vec<_>>(),
+            vec![10, 0]
+        );
+    }
+
+    #[test]
+    fn forgets_the_history_of_deleted_series() {
+        let conn = db::open_in_memory();
+        let mut kept = Series::new("Kept".to_string(), None, None, Some(12), None, None);
+        let mut gone = Series::new("Gone".to_string(), None, None, Some(12), None, None);
+        kept.insert(&conn).unwrap();
+        gone.insert(&conn).unwrap();
+        kept.try_watch(&conn, 1).unwrap();
+        gone.try_watch(&conn, 1).unwrap();
+
+        gone.try_delete(&conn).unwrap();
+        // Still there for undo
+        assert_eq!(WatchEvent::for_series(&conn, &gone.guid).unwrap().len(), 1);
+
+        WatchEvent::forget_orphans(&conn).unwrap();
+        assert!(WatchEvent::for_series(&conn, &gone.guid)
+            .unwrap()
+            .is_empty());
+        assert_eq!(WatchEvent::for_series(&conn, &kept.guid).unwrap().len(), 1);
+    }
+}
@@ -1,10 +1,177 @@
 use std::io;
 use std::io::Write;
 
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    app::App,
+    term::{
+        self,
+        key::{read_key, Chord, Key},
+        raw, screen,
+    },
+};
+
+const PROMPT: &str = "|> ";
+
+/// What `get` returns when the terminal was resized instead, handlers only need to redraw
+pub(crate) const RESIZED: &str = "<Resize>";
+
+/// Reads one keybinding, like `j`, `12k` or `gg`. Falls back to a whole line when stdin is not a terminal.
 pub(crate) fn get() -> String {
+    if !raw::is_enabled() {
+        return read_line();
+    }
+
+    let mut chord = Chord::default();
+    let mut prompt = Prompt::default();
+
+    loop {
+        prompt.draw(&format!("{PROMPT}{}", chord.as_str()));
+
+        let key = next_key();
+        if key == Key::Resize {
+            return RESIZED.to_string();
+        }
+
+        if let Some(chord) = chord.push(key) {
+            return chord;
+        }
+    }
+}
+
+/// Like `get`, for the answer to `prompt` shown as the toast. A resize redraws the screen
+/// without it, so the prompt is put back and `None` returned to be asked again.
+pub(crate) fn get_answer(app: &mut App, prompt: &str) -> Option<String> {
+    let answer = get();
+    if answer == RESIZED {
+        app.toast = Some(prompt.to_string());
+        return None;
+    }
+
+    Some(answer)
+}
+
+/// Reads a single key after showing `prompt`, `None` when stdin is not a terminal
+pub(crate) fn get_key(prompt: &str) -> Option<Key> {
+    if !raw::is_enabled() {
+        return None;
+    }
+
+    Prompt::default().draw(prompt);
+
+    // The prompt is still on screen, so there is nothing to redraw
+    loop {
+        match next_key() {
+            Key::Resize => continue,
+            key => return Some(key),
+        }
+    }
+}
+
+/// Reads a line of text starting from `initial`, `None` when cancelled with Esc
+pub(crate) fn get_line(initial: &str) -> Option<String> {
+    if !raw::is_enabled() {
+        return Some(read_line().trim().to_string());
+    }
+
+    let mut line = initial.to_string();
+    let mut prompt = Prompt::default();
+    let _cursor = screen::show_cursor();
+
+    loop {
+        prompt.draw(&format!("{PROMPT}{line}"));
+
+        match next_key() {
+            Key::Enter => return Some(line),
+            Key::Esc => return None,
+            Key::Backspace => {
+                line.pop();
+            }
+            Key::Ctrl('u') => line.clear(),
+            Key::Char(c) => line.push(c),
+            _ => (),
+        }
+    }
+}
+
+/// Like `get_line`, but Enter starts a new line and Ctrl-D finishes
+pub(crate) fn get_text(initial: &str) -> Option<String> {
+    if !raw::is_enabled() {
+        return Some(read_line().trim().to_string());
+    }
+
+    let mut text = initial.to_string();
+    let mut prompt = Prompt::default();
+    let _cursor = screen::show_cursor();
+
+    loop {
+        prompt.draw(&format!(
+            "(Ctrl-D to finish, Esc to cancel)\n{PROMPT}{}",
+            text.replace('\n', &format!("\n{PROMPT}"))
+        ));
+
+        match next_key() {
+            Key::Ctrl('d') => return Some(text),
+            Key::Esc => return None,
+            Key::Enter => text.push('\n'),
+            Key::Backspace => {
+                text.pop();
+            }
+            Key::Char(c) => text.push(c),
+            _ => (),
+        }
+    }
+}
+
+/// What was last printed below the frame, so the next print can go over all of it
+#[derive(Default)]
+struct Prompt {
+    rows: usize,
+}
+
+impl Prompt {
+    /// Prints `text` over the previous one. Text taking more than one row may have scrolled the
+    /// frame, so the next frame is then drawn from scratch.
+    fn draw(&mut self, text: &str) {
+        // Back to where the previous draw started
+        if self.rows > 1 {
+            print!("{CSI}{}A", self.rows - 1, CSI = term::CSI);
+        }
+        print!("\r{CSI}J{}", text.replace('\n', "\r\n"), CSI = term::CSI);
+        io::stdout().flush().unwrap();
+
+        let cols = term::get_size().map_or(80, |size| usize::from(size.cols).max(1));
+        self.rows = text
+            .split('\n')
+            .map(|line| line.width().div_ceil(cols).max(1))
+            .sum();
+
+        if self.rows > 1 {
+            screen::invalidate();
+        }
+    }
+}
+
+fn next_key() -> Key {
+    match read_key() {
+        Ok(Key::Ctrl('c')) | Err(_) => exit(),
+        Ok(key) => key,
+    }
+}
+
+// Ctrl-C no longer raises SIGINT in raw mode, so quit by hand without leaving the terminal broken
+fn exit() -> ! {
+    raw::disable();
+    screen::leave();
+
+    std::process::exit(130);
+}
+
+fn read_line() -> String {
     let mut input: String = String::new();
 
-    print!("|> ");
+    print!("{PROMPT}");
     io::stdout().flush().unwrap();
     io::stdin().read_line(&mut input).unwrap();
 
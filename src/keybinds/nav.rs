@@ -1,9 +1,10 @@
-use std::{ops::Add, sync::Arc};
+use std::sync::Arc;
 
 use super::UserInput;
 use crate::{
     app::App,
-    main_key_handler, main_render,
+    main_key_handler, main_render, season,
+    series::Series,
     user::{
         create_input::CreateInput,
         create_series::{create_key_handler, create_render},
@@ -11,87 +12,111 @@ use crate::{
     },
 };
 
-pub fn move_down(app: &mut App, input: &UserInput) {
-    let digits_prefix = input.digits_prefix.unwrap_or(1);
-    let max = app.data.take.saturating_sub(1);
-
-    app.data.hovered_series_idx = app
-        .data
-        .hovered_series_idx
-        .saturating_add(digits_prefix)
-        .min(max);
+pub(crate) fn move_down(app: &mut App, input: &UserInput) {
+    let count = input.digits_prefix.unwrap_or(1);
+    app.data.move_by(count as isize);
 }
 
 pub(crate) fn move_up(app: &mut App, input: &UserInput) {
-    let digits_prefix = input.digits_prefix.unwrap_or(1);
-
-    app.data.hovered_series_idx = app
-        .data
-        .hovered_series_idx
-        .saturating_sub(digits_prefix)
-        .max(0);
+    let count = input.digits_prefix.unwrap_or(1);
+    app.data.move_by(-(count as isize));
 }
 
 pub(crate) fn move_min(app: &mut App) {
-    app.data.hovered_series_idx = 0;
+    app.data.jump_to(0);
 }
 
 pub(crate) fn move_max(app: &mut App) {
-    app.data.hovered_series_idx = app.data.take.saturating_sub(1);
+    app.data.jump_to(app.data.total_series.saturating_sub(1));
 }
 
+/// Counts from 1, like the id column
 pub(crate) fn move_to(app: &mut App, input: &UserInput) {
-    let max = app.data.take;
-    let selected = input.digits_prefix.unwrap_or(0);
-    app.data.hovered_series_idx = selected.clamp(0, max).saturating_sub(1);
+    let position = input.digits_prefix.unwrap_or(1).saturating_sub(1);
+    app.data.jump_to(position);
 }
 
 pub(crate) fn scroll_up(app: &mut App, input: &UserInput) {
-    if app.data.skip == 0 {
-        return;
-    }
-
-    let offset = -(input.digits_prefix.unwrap_or(1) as i32);
-    scroll(app, offset);
+    let count = input.digits_prefix.unwrap_or(1);
+    app.data.scroll_by(-(count as isize));
 }
 
 pub(crate) fn scroll_down(app: &mut App, input: &UserInput) {
-    let offset = input.digits_prefix.unwrap_or(1);
-    let window_size = app.data.take + offset;
+    let count = input.digits_prefix.unwrap_or(1);
+    app.data.scroll_by(count as isize);
+}
 
-    if window_size > app.data.total_series {
-        return;
-    }
+/// Scrolls and moves by half a page, like Ctrl-D/Ctrl-U in vim
+pub(crate) fn half_page_down(app: &mut App) {
+    let half = app.data.half_page();
+    let target = app.data.cursor() + half;
 
-    scroll(app, offset.try_into().unwrap());
+    app.data.scroll_by(half as isize);
+    app.data.jump_to(target);
 }
 
-fn scroll(app: &mut App, qty: i32) {
-    let new_skip = qty.add(app.data.skip as i32) as usize;
-    app.data.skip = new_skip;
-    app.data.ignore_cached_series = true;
+pub(crate) fn half_page_up(app: &mut App) {
+    let half = app.data.half_page();
+    let target = app.data.cursor().saturating_sub(half);
+
+    app.data.scroll_by(-(half as isize));
+    app.data.jump_to(target);
 }
 
 pub(crate) fn start_listing(app: &mut App) {
     app.keyboard_handler = Arc::new(main_key_handler);
     app.renderer = Arc::new(main_render);
-    app.data.restore_take();
-
-    move_min(app);
+    app.edited_series = None;
 }
 
 pub(crate) fn start_inserting(app: &mut App) {
+    open_form(app, None);
+}
+
+pub(crate) fn start_editing(app: &mut App) {
+    let Some(series) = app.data.available_series.get(app.data.hovered_series_idx) else {
+        return;
+    };
+
+    open_form(app, Some(series.clone()));
+}
+
+pub(crate) fn open_form(app: &mut App, series: Option<Series>) {
     app.keyboard_handler = Arc::new(create_key_handler);
     app.renderer = Arc::new(create_render);
 
-    app.create_data = vec![
-        CreateInput::new("Name", InputType::String),
+    let mut form = vec![
+        CreateInput::new("Name", InputType::String).required(),
         CreateInput::new("Finished?", InputType::Boolean),
         CreateInput::new("Airing Finished?", InputType::Boolean),
         CreateInput::new("Current Episode", InputType::Number),
         CreateInput::new("Total Episodes", InputType::Number),
+        CreateInput::new("Status", InputType::Status),
+        CreateInput::new("Rating", InputType::Rating),
+        CreateInput::new("Notes", InputType::Text),
+        CreateInput::new("Tags", InputType::Tags),
+        CreateInput::new("Seasons", InputType::Seasons),
     ];
-    app.data.set_take(app.create_data.len());
 
-    move_min(app);
+    if let Some(series) = &series {
+        form[0].set_value(&series.name);
+        form[1].set_value(series.is_finished);
+        form[2].set_value(series.is_airing_finished);
+        form[3].set_value(series.current_episode);
+        form[4].set_value(series.total_episodes);
+        form[5].set_value(series.status.label());
+        form[6].set_value(
+            series
+                .rating
+                .map(|rating| rating.to_string())
+                .unwrap_or_default(),
+        );
+        form[7].set_value(&series.notes);
+        form[8].set_value(series.tags.join(", "));
+        form[9].set_value(season::format(&series.seasons));
+    }
+
+    app.create_data = form;
+    app.edited_series = series;
+    app.hovered_field_idx = 0;
 }
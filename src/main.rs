@@ -1,27 +1,83 @@
 use crate::term::{attribute, color};
 use app::{App, Data};
 use color::Color;
+use history::WatchEvent;
 use keybinds::{
     nav::{
-        move_down, move_max, move_min, move_to, move_up, scroll_down, scroll_up, start_inserting,
+        half_page_down, half_page_up, move_down, move_max, move_min, move_to, move_up, scroll_down,
+        scroll_up, start_editing, start_inserting,
     },
     parse_input, Mode,
 };
-use printer::print_series_table;
-use series::Series;
+use printer::{print_series_table, print_status_tabs};
+use series::{Series, Sort};
 use std::sync::Arc;
-use term::clear_screen;
+use term::screen::{self, drawln};
+use user::{
+    delete_series::{forget_deleted, start_deleting, undo_delete},
+    history_series::start_history,
+    search_series::{clear_search, start_searching},
+    series_details::start_details,
+    sort_series::{cycle_sort_key, toggle_sort_direction},
+    stats_dashboard::start_stats,
+    status_filter::{next_status_view, previous_status_view},
+    tag_filter::{start_tag_filter, toggle_tag_match},
+    watch_series::{watch_next, watch_previous},
+};
 
 mod app;
+mod args;
+mod cli;
 mod db;
+mod history;
 mod input;
 mod keybinds;
+mod mal;
 mod printer;
+mod season;
 mod series;
+mod stats;
 mod term;
+mod transfer;
 mod user;
 
 fn main() {
+    let args = match args::parse(std::env::args()) {
+        Ok(args) => args,
+        Err(er) => {
+            eprintln!("{er}\n{}", cli::USAGE_HINT);
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    };
+
+    color::init(args.color);
+
+    if let Err(er) = db::init(args.db) {
+        eprintln!("Could not create the database directory: {er}");
+        std::process::exit(cli::EXIT_ERROR);
+    }
+
+    let conn = match db::get_connection() {
+        Ok(conn) => conn,
+        Err(er) => {
+            eprintln!("Could not open the database {}: {er}", db::path().display());
+            std::process::exit(cli::EXIT_ERROR);
+        }
+    };
+
+    if let Some(command) = &args.command {
+        std::process::exit(cli::run(command, &args.rest, args.json));
+    }
+
+    // Nothing from a previous run can be undone anymore
+    if let Err(er) = WatchEvent::forget_orphans(&conn) {
+        eprintln!("Could not clean up the watch history: {er}");
+    }
+
+    let raw_mode = term::raw::enable();
+    let alt_screen = screen::enter();
+    term::resize::listen();
+
     let mut app = App {
         should_render: true,
         should_exit: false,
@@ -34,15 +90,21 @@ fn main() {
         toast: None,
         data: Data::default(Series::count_total().unwrap_or(0)),
         create_data: vec![],
+        hovered_field_idx: 0,
+        edited_series: None,
     };
+    app.data.query.sort = Sort::load(&conn).unwrap_or_default();
 
     while !app.should_exit {
-        let sizes = term::get_size().unwrap();
-        let has_term_resized = app.term_size.cols != sizes.cols || app.term_size.rows != sizes.rows;
-
-        if app.should_render || !has_term_resized {
-            app.term_size = sizes;
+        if term::resize::take() {
+            if let Some(size) = term::get_size() {
+                app.term_size = size;
+            }
+            screen::invalidate();
+            app.should_render = true;
+        }
 
+        if app.should_render {
             render(&mut app);
             app.should_render = false;
         }
@@ -50,7 +112,9 @@ fn main() {
         app.keyboard_handler.clone()(&mut app);
     }
 
-    clear_screen();
+    forget_deleted(&mut app);
+    drop(alt_screen);
+    drop(raw_mode);
 }
 
 fn print_help() {
@@ -58,14 +122,45 @@ fn print_help() {
         ("h", "Toggle Help"),
         ("m", "Toggle help Message"),
         ("r", "Force Refresh the series"),
-        ("\\d+(j|k)", "Move down or up by this many"),
-        ("G", "Move to last series"),
-        ("gg", "Move to first series"),
+        ("i", "Insert a new series"),
+        ("e", "Edit the hovered series"),
+        ("dd", "Delete the hovered series"),
+        ("u", "Undo the last delete"),
+        ("H", "Watch history of the hovered series"),
+        ("t", "Statistics"),
+        ("/", "Search by name, Esc clears it"),
+        (
+            "C-n|C-p",
+            "Next or previous match while typing the search, arrows work too",
+        ),
+        ("f", "Filter by tags, comma separated"),
+        ("F", "Match all of the filtered tags, or any of them"),
+        ("s", "Sort by the next column"),
+        ("S", "Flip the sort between ascending and descending"),
+        (
+            "Tab",
+            "Show the next status, Shift-Tab for the previous one",
+        ),
+        (
+            "\\d+(n|p)",
+            "Mark this many episodes as watched or unwatched",
+        ),
+        ("\\d+(j|k)", "Move down or up by this many, arrows work too"),
+        ("G", "Move to last series, or End"),
+        ("gg", "Move to first series, or Home"),
+        (
+            "\\d+(-|+)",
+            "Scroll up or down by this many, PageUp/PageDown work too",
+        ),
+        ("C-d|C-u", "Move down or up by half a page"),
+        ("\\d+", "Move to this series, then Enter"),
+        ("Enter", "Show everything about the hovered series"),
+        ("q", "Quit, or Ctrl-C from anywhere"),
     ];
 
     for (key, desc) in keybinds.iter() {
-        println!(
-            "{}{: >4}{:?}: {}",
+        drawln!(
+            "{}{: >8}{}: {}",
             Color::Magenta,
             key,
             attribute::reset(),
@@ -75,47 +170,98 @@ fn print_help() {
 }
 
 fn render(app: &mut App) {
-    clear_screen();
+    screen::begin();
+    draw_frame(app);
+    screen::present(app.term_size.rows as usize, app.term_size.cols as usize);
+}
 
+fn draw_frame(app: &mut App) {
     if app.should_show_help {
         print_help();
-        println!();
+        drawln!();
         return;
     }
 
     app.renderer.clone()(app);
-    println!();
+    drawln!();
 
-    match &app.toast {
-        Some(msg) => {
-            println!("Note: {}", msg);
-            app.toast = None;
-        }
-        None => (),
+    if let Some(msg) = app.toast.take() {
+        drawln!("Note: {}", msg);
     }
 
     if app.should_show_help_msg {
-        println!("Press [h] for keybinds");
+        drawln!("Press [h] for keybinds");
     }
 }
 
 fn main_render(app: &mut App) {
     if app.should_show_help {
         print_help();
-        println!();
+        drawln!();
         return;
     }
 
+    let take = (app.term_size.rows as usize)
+        .saturating_sub(reserved_lines(app))
+        .max(1);
+    app.data.set_take(take);
+
     let data = &mut app.data;
 
     if data.available_series.is_empty() || data.ignore_cached_series {
-        let series = Series::get(data.take, data.skip).unwrap_or_else(|_| vec![]);
+        data.total_series = Series::count(&data.query).unwrap_or(0);
+        data.status_counts = Series::count_by_status(&data.query).unwrap_or_default();
+        data.clamp_cursor();
+
+        let series = Series::get(data.take, data.skip, &data.query).unwrap_or_else(|_| vec![]);
         data.available_series.clone_from(&series);
 
         data.ignore_cached_series = false;
     }
 
-    print_series_table(&data.available_series, data.hovered_series_idx);
+    print_status_tabs(data.query.status, &data.status_counts);
+
+    if let Some(search) = &data.query.search {
+        drawln!("Search: /{} ({} matches)", search, data.total_series);
+    }
+
+    if !data.query.tags.is_empty() {
+        drawln!(
+            "Tags: {} (matching {})",
+            data.query.tags.join(", "),
+            data.query.tag_match.label()
+        );
+    }
+
+    print_series_table(
+        &data.available_series,
+        data.hovered_series_idx,
+        data.skip,
+        &data.query,
+        app.term_size.cols as usize,
+    );
+}
+
+/// Lines printed around the table rows, by `main_render` and `render` after it
+fn reserved_lines(app: &App) -> usize {
+    let data = &app.data;
+
+    // Status tabs, sort line, table header and separator
+    let mut lines = 4;
+    lines += usize::from(data.query.search.is_some());
+    lines += usize::from(!data.query.tags.is_empty());
+
+    lines + frame_lines(app)
+}
+
+/// Lines `draw_frame` adds under whatever the renderer drew, the prompt included
+fn frame_lines(app: &App) -> usize {
+    // The blank line and the prompt
+    let mut lines = 2;
+    lines += app.toast.as_ref().map_or(0, |toast| toast.lines().count());
+    lines += usize::from(app.should_show_help_msg);
+
+    lines
 }
 
 fn main_key_handler(app: &mut App) {
@@ -136,21 +282,41 @@ fn main_key_handler(app: &mut App) {
         "r" => app.data.ignore_cached_series = true,
 
         "i" => start_inserting(app),
+        "e" => start_editing(app),
+        "dd" => start_deleting(app),
+        "u" => undo_delete(app),
+        "H" => start_history(app),
+        "t" => start_stats(app),
 
-        "k" => move_up(app, &input),
-        "j" => move_down(app, &input),
+        "/" => start_searching(app),
+        "<Esc>" => clear_search(app),
 
-        "G" => move_max(app),
-        "gg" => move_min(app),
+        "f" => start_tag_filter(app),
+        "F" => toggle_tag_match(app),
 
-        "-" => scroll_up(app, &input),
-        "+" => scroll_down(app, &input),
+        "s" => cycle_sort_key(app),
+        "S" => toggle_sort_direction(app),
 
-        "" => {
-            if input.digits_prefix.is_some() {
-                move_to(app, &input);
-            }
-        }
+        "<Tab>" => next_status_view(app),
+        "<S-Tab>" => previous_status_view(app),
+
+        "n" => watch_next(app, &input),
+        "p" => watch_previous(app, &input),
+
+        "k" | "<Up>" => move_up(app, &input),
+        "j" | "<Down>" => move_down(app, &input),
+
+        "G" | "<End>" => move_max(app),
+        "gg" | "<Home>" => move_min(app),
+
+        "-" | "<PageUp>" => scroll_up(app, &input),
+        "+" | "<PageDown>" => scroll_down(app, &input),
+
+        "<C-d>" => half_page_down(app),
+        "<C-u>" => half_page_up(app),
+
+        "" | "<CR>" if input.digits_prefix.is_some() => move_to(app, &input),
+        "<CR>" => start_details(app),
 
         _ => {}
     }
@@ -0,0 +1,239 @@
+use std::io::BufRead;
+
+use quick_xml::{events::Event, Reader};
+use rusqlite::Connection;
+
+use crate::{
+    series::{Series, WatchStatus},
+    transfer::TransferError,
+};
+
+/// One `<anime>` entry of a MyAnimeList export, only the parts we keep
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct MalEntry {
+    pub(crate) title: String,
+    pub(crate) episodes: i32,
+    pub(crate) watched_episodes: i32,
+    pub(crate) status: String,
+}
+
+impl MalEntry {
+    /// MAL has no "on hold", the closest is still watching. Older exports use numbers.
+    fn watch_status(&self) -> Option<WatchStatus> {
+        match self.status.trim() {
+            "Watching" | "1" => Some(WatchStatus::Watching),
+            "Completed" | "2" => Some(WatchStatus::Completed),
+            "On-Hold" | "3" => Some(WatchStatus::Watching),
+            "Dropped" | "4" => Some(WatchStatus::Dropped),
+            "Plan to Watch" | "6" => Some(WatchStatus::PlanToWatch),
+            _ => None,
+        }
+    }
+
+    fn into_series(self) -> Series {
+        let status = self.watch_status();
+        let is_completed = status == Some(WatchStatus::Completed);
+
+        // MAL uses 0 episodes for "still airing, count unknown"
+        let current_episode = match self.episodes > 0 {
+            true => self.watched_episodes.min(self.episodes),
+            false => self.watched_episodes,
+        };
+
+        Series::new(
+            self.title,
+            Some(is_completed),
+            // Finishing a series means it is done airing too, otherwise MAL doesn't say
+            Some(is_completed),
+            Some(self.episodes.max(0)),
+            Some(current_episode.max(0)),
+            status,
+        )
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct MalSummary {
+    pub(crate) inserted: Vec<String>,
+    /// Names already in the library, or repeated in the file
+    pub(crate) skipped: Vec<String>,
+}
+
+pub(crate) fn parse(reader: impl BufRead) -> Result<Vec<MalEntry>, TransferError> {
+    let mut reader = Reader::from_reader(reader);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = vec![];
+    let mut entry: Option<MalEntry> = None;
+    let mut text = String::new();
+    let mut buf = vec![];
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(tag) if tag.name().as_ref() == b"anime" => {
+                entry = Some(MalEntry::default());
+            }
+            Event::Start(_) => text.clear(),
+            Event::Text(content) => text.push_str(&content.unescape()?),
+            Event::CData(content) => text.push_str(&String::from_utf8_lossy(&content)),
+            Event::End(tag) => {
+                let Some(current) = entry.as_mut() else {
+                    continue;
+                };
+
+                match tag.name().as_ref() {
+                    b"anime" => entries.extend(entry.take()),
+                    b"series_title" => current.title = text.trim().to_string(),
+                    b"series_episodes" => current.episodes = text.trim().parse().unwrap_or(0),
+                    b"my_watched_episodes" => {
+                        current.watched_episodes = text.trim().parse().unwrap_or(0)
+                    }
+                    b"my_status" => current.status = text.trim().to_string(),
+                    _ => (),
+                }
+
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+
+        buf.clear();
+    }
+
+    Ok(entries)
+}
+
+/// Inserts every entry whose name isn't in the library yet, all in one transaction
+pub(crate) fn import(
+    conn: &mut Connection,
+    entries: Vec<MalEntry>,
+    dry_run: bool,
+) -> Result<MalSummary, TransferError> {
+    let tx = conn.transaction()?;
+    let mut summary = MalSummary::default();
+
+    for entry in entries {
+        if entry.title.is_empty() {
+            continue;
+        }
+
+        if Series::get_by_name(&tx, &entry.title)?.is_some() {
+            summary.skipped.push(entry.title);
+            continue;
+        }
+
+        let mut series = entry.into_series();
+        series.insert(&tx)?;
+        summary.inserted.push(series.name);
+    }
+
+    match dry_run {
+        true => tx.rollback()?,
+        false => tx.commit()?,
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, series::Query};
+
+    const ANIMELIST: &str = include_str!("../tests/fixtures/animelist.xml");
+    const NUMERIC_STATUS: &str = include_str!("../tests/fixtures/animelist_numeric_status.xml");
+
+    fn all_series(conn: &Connection) -> Vec<Series> {
+        Series::get_in(conn, 100, 0, &Query::default()).unwrap()
+    }
+
+    #[test]
+    fn parses_entries() {
+        let entries = parse(ANIMELIST.as_bytes()).unwrap();
+
+        assert_eq!(entries.len(), 6);
+        assert_eq!(
+            entries[0],
+            MalEntry {
+                title: "Sousou no Frieren".to_string(),
+                episodes: 28,
+                watched_episodes: 28,
+                status: "Completed".to_string(),
+            }
+        );
+        assert_eq!(entries[3].title, "Jujutsu Kaisen & Friends");
+    }
+
+    #[test]
+    fn imports_and_skips_duplicate_names() {
+        let mut conn = db::open_in_memory();
+        let entries = parse(ANIMELIST.as_bytes()).unwrap();
+
+        let summary = import(&mut conn, entries, false).unwrap();
+
+        assert_eq!(summary.inserted.len(), 5);
+        assert_eq!(summary.skipped, vec!["SOUSOU NO FRIEREN"]);
+
+        let series = all_series(&conn);
+        let by_name = |name: &str| series.iter().find(|series| series.name == name).unwrap();
+
+        let frieren = by_name("Sousou no Frieren");
+        assert_eq!(frieren.status, WatchStatus::Completed);
+        assert!(frieren.is_finished);
+        assert_eq!((frieren.current_episode, frieren.total_episodes), (28, 28));
+
+        assert_eq!(by_name("Bocchi the Rock!").status, WatchStatus::Watching);
+        assert_eq!(by_name("One Piece").current_episode, 1071);
+        assert_eq!(by_name("One Piece").status, WatchStatus::Watching);
+        assert_eq!(
+            by_name("Jujutsu Kaisen & Friends").status,
+            WatchStatus::Dropped
+        );
+        assert_eq!(
+            by_name("Kusuriya no Hitorigoto 2nd Season").status,
+            WatchStatus::PlanToWatch
+        );
+
+        // A second run finds everything in the library already
+        let entries = parse(ANIMELIST.as_bytes()).unwrap();
+        let summary = import(&mut conn, entries, false).unwrap();
+
+        assert!(summary.inserted.is_empty());
+        assert_eq!(summary.skipped.len(), 6);
+    }
+
+    #[test]
+    fn maps_numeric_statuses() {
+        let mut conn = db::open_in_memory();
+        let entries = parse(NUMERIC_STATUS.as_bytes()).unwrap();
+
+        import(&mut conn, entries, false).unwrap();
+
+        let statuses = all_series(&conn)
+            .into_iter()
+            .map(|series| (series.name, series.status))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses,
+            vec![
+                ("Dandadan".to_string(), WatchStatus::Watching),
+                ("Dungeon Meshi".to_string(), WatchStatus::Completed),
+                // Unknown codes fall back to guessing from the progress
+                ("Untitled entry".to_string(), WatchStatus::PlanToWatch),
+            ]
+        );
+    }
+
+    #[test]
+    fn dry_run_leaves_the_library_alone() {
+        let mut conn = db::open_in_memory();
+        let entries = parse(ANIMELIST.as_bytes()).unwrap();
+
+        let summary = import(&mut conn, entries, true).unwrap();
+
+        assert_eq!(summary.inserted.len(), 5);
+        assert!(all_series(&conn).is_empty());
+    }
+}
@@ -1,79 +1,374 @@
-use crate::series::Series;
-use crate::term;
-use crate::term::{attribute, color::Color};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-pub(crate) fn print_series_table(printable_series: &[Series], selected_idx: usize) {
-    print_header();
-    print_separator("-", true);
+use crate::series::{Query, Series, Sort, WatchStatus};
+use crate::term::{
+    attribute,
+    color::Color,
+    screen::{draw, drawln},
+};
 
-    let series = printable_series.iter();
-    for (i, series) in series.enumerate() {
-        let is_selected = i == selected_idx;
+const SEPARATOR: &str = " | ";
+const ELLIPSIS: char = '…';
 
-        print_row(series, i as i32, is_selected);
-        println!();
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+}
+
+struct Column {
+    title: &'static str,
+    align: Align,
+    color: Option<Color>,
+    /// How narrow it may get when the terminal is, `None` keeps the content width
+    min: Option<usize>,
+    /// Never wider than this, even with room to spare
+    max: Option<usize>,
+}
+
+impl Column {
+    const fn new(title: &'static str, align: Align, color: Option<Color>) -> Self {
+        Self {
+            title,
+            align,
+            color,
+            min: None,
+            max: None,
+        }
+    }
+
+    const fn shrinks_to(mut self, min: usize) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    const fn at_most(mut self, max: usize) -> Self {
+        self.max = Some(max);
+        self
     }
 }
 
-fn print_header() {
-    println!("Id | Guid | Name | Finished | Airing Finished | Total Episodes | Current Episode");
+// Left to right, the last ones are dropped first when nothing else fits
+const COLUMNS: [Column; 10] = [
+    Column::new("Id", Align::Right, None),
+    Column::new("Guid", Align::Left, Some(Color::Rgb(190, 190, 190))).at_most(8),
+    Column::new("Name", Align::Left, Some(Color::Green)).shrinks_to(8),
+    Column::new("Finished", Align::Left, Some(Color::Red)),
+    Column::new("Airing Finished", Align::Left, Some(Color::Blue)),
+    Column::new("Total Episodes", Align::Right, Some(Color::Green)),
+    Column::new("Current Episode", Align::Right, Some(Color::Green)),
+    Column::new("Next", Align::Left, Some(Color::Green)),
+    Column::new("Rating", Align::Right, Some(Color::Magenta)),
+    Column::new("Tags", Align::Left, Some(Color::Blue)).shrinks_to(4),
+];
+
+/// Columns meant to shrink give up their spare room first, down to a few times their minimum,
+/// then long titles over narrow content, then whatever the shrinking columns have left
+const SHRINK_STAGES: [(bool, usize); 3] = [(true, 3), (false, 1), (true, 1)];
+
+/// Index of the name column, the only one search matches are underlined in
+const NAME: usize = 2;
+
+/// Behind search matches, the underline is still there for when colors are off
+const HIGHLIGHT: Color = Color::Rgb(135, 135, 0);
+
+pub(crate) fn print_series_table(
+    printable_series: &[Series],
+    selected_idx: usize,
+    offset: usize,
+    query: &Query,
+    width: usize,
+) {
+    let rows = printable_series
+        .iter()
+        .enumerate()
+        .map(|(i, series)| cells(series, offset + i))
+        .collect::<Vec<_>>();
+    let widths = fit_widths(&rows, width);
+
+    print_header(&query.sort, &widths);
+    print_separator(&widths);
+
+    for (i, row) in rows.iter().enumerate() {
+        print_row(row, &widths, i == selected_idx, query.search.as_deref());
+        drawln!();
+    }
 }
 
-fn print_separator(sep: &str, do_repeat: bool) {
-    let (width, _) = match term::get_size() {
-        Some(sizes) => (sizes.cols, sizes.rows),
-        None => (0, 0),
-    };
+/// One tab per status plus "All", the active one inverted
+pub(crate) fn print_status_tabs(active: Option<WatchStatus>, counts: &[(WatchStatus, usize)]) {
+    let total = counts.iter().map(|(_, count)| count).sum::<usize>();
+
+    let mut tabs = vec![(None, "All", total)];
+    tabs.extend(
+        counts
+            .iter()
+            .map(|(status, count)| (Some(*status), status.label(), *count)),
+    );
 
-    let sep = if do_repeat {
-        &sep.repeat(width as usize - sep.len() + 1).to_string()
-    } else {
-        sep
+    for (status, label, count) in tabs {
+        let style = match status == active {
+            true => attribute::invert(),
+            false => String::new(),
+        };
+
+        draw!("{style} {label} {count} {}", attribute::reset());
+    }
+    drawln!();
+}
+
+fn cells(series: &Series, idx: usize) -> Vec<String> {
+    let rating = match series.rating {
+        Some(rating) => rating.to_string(),
+        None => "-".to_string(),
     };
 
-    print!("{}", sep);
+    vec![
+        (idx + 1).to_string(),
+        series.guid.clone(),
+        series.name.clone(),
+        series.is_finished.to_string(),
+        series.is_airing_finished.to_string(),
+        series.total_episodes.to_string(),
+        series.current_episode.to_string(),
+        series.next_episode().unwrap_or_else(|| "-".to_string()),
+        rating,
+        series.tags.join(", "),
+    ]
+}
+
+/// Widths per column, `None` for the ones that had to go. Starts from the widest content and
+/// takes from the widest shrinkable column until the table fits `width`.
+fn fit_widths(rows: &[Vec<String>], width: usize) -> Vec<Option<usize>> {
+    let content = |col: usize| rows.iter().map(|row| row[col].width()).max().unwrap_or(0);
+
+    let mut widths = vec![];
+    let mut mins = vec![];
+    for (col, column) in COLUMNS.iter().enumerate() {
+        let natural = content(col).max(column.title.width());
+        let natural = column.max.map_or(natural, |max| natural.min(max));
+
+        // Titles give way before content does
+        let min = column.min.unwrap_or(content(col).max(1)).min(natural);
+
+        widths.push(Some(natural));
+        mins.push(min);
+    }
+
+    while table_width(&widths) > width {
+        let shrinkable = SHRINK_STAGES.iter().find_map(|(meant_to_shrink, factor)| {
+            (0..COLUMNS.len())
+                .filter(|col| COLUMNS[*col].min.is_some() == *meant_to_shrink)
+                .filter_map(|col| {
+                    let floor = mins[col] * factor;
+                    let width = widths[col].filter(|width| *width > floor)?;
+                    Some((col, width - floor))
+                })
+                .max_by_key(|(col, slack)| (*slack, *col))
+        });
+
+        match shrinkable {
+            Some((col, _)) => widths[col] = widths[col].map(|width| width - 1),
+            None => match widths.iter().rposition(Option::is_some) {
+                // Always keep the id and the name
+                Some(col) if col > NAME => widths[col] = None,
+                _ => break,
+            },
+        }
+    }
+
+    widths
+}
+
+fn table_width(widths: &[Option<usize>]) -> usize {
+    let shown = widths.iter().flatten().count();
+    widths.iter().flatten().sum::<usize>() + SEPARATOR.len() * shown.saturating_sub(1)
+}
+
+fn print_header(sort: &Sort, widths: &[Option<usize>]) {
+    let direction = if sort.descending { "desc" } else { "asc" };
+    drawln!("Sorted by {} ({direction})", sort.key.label());
+
+    let titles = COLUMNS
+        .iter()
+        .zip(widths)
+        .filter_map(|(column, width)| width.map(|width| fit(column.title, width, column.align)))
+        .collect::<Vec<_>>();
+
+    drawln!("{}", titles.join(SEPARATOR));
+}
+
+fn print_separator(widths: &[Option<usize>]) {
+    drawln!("{}", "-".repeat(table_width(widths)));
 }
 
-fn print_row(series: &Series, idx: i32, is_selected: bool) {
-    let invert = attribute::invert();
-    let attribute = match is_selected {
-        true => Some(invert.as_str()),
-        false => None,
+fn print_row(row: &[String], widths: &[Option<usize>], is_selected: bool, highlight: Option<&str>) {
+    // Re-applied after every reset, so the inversion runs through padding and separators too
+    let style = match is_selected {
+        true => attribute::invert(),
+        false => String::new(),
     };
 
-    print_cell(idx + 1, None, None);
-    print_separator(" | ", false);
+    let mut is_first = true;
+    for (col, ((column, cell), width)) in COLUMNS.iter().zip(row).zip(widths).enumerate() {
+        let Some(width) = width else {
+            continue;
+        };
 
-    print_cell(
-        series.guid[..4].to_owned() + "...",
-        Some(Color::Rgb(190, 190, 190)),
-        attribute,
-    );
-    print_separator(" | ", false);
+        if !is_first {
+            draw!("{style}{SEPARATOR}");
+        }
+        is_first = false;
+
+        let cell = fit(cell, *width, column.align);
+        match highlight.filter(|highlight| !highlight.is_empty()) {
+            Some(highlight) if col == NAME => {
+                print_highlighted_cell(&cell, highlight, column.color.clone(), &style)
+            }
+            _ => print_cell(&cell, column.color.clone(), &style),
+        }
+    }
+
+    draw!("{}", attribute::reset());
+}
+
+/// Pads `cell` to exactly `width` columns, cutting it short with an ellipsis when it's wider
+fn fit(cell: &str, width: usize, align: Align) -> String {
+    let cell = truncate(cell, width);
+    let padding = " ".repeat(width.saturating_sub(cell.width()));
+
+    match align {
+        Align::Left => format!("{cell}{padding}"),
+        Align::Right => format!("{padding}{cell}"),
+    }
+}
 
-    print_cell(&series.name, Some(Color::Green), attribute);
-    print_separator(" | ", false);
+fn truncate(cell: &str, width: usize) -> String {
+    if cell.width() <= width {
+        return cell.to_string();
+    }
 
-    print_cell(series.is_finished, Some(Color::Red), attribute);
-    print_separator(" | ", false);
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in cell.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
 
-    print_cell(series.is_airing_finished, Some(Color::Blue), attribute);
-    print_separator(" | ", false);
+        truncated.push(c);
+        used += char_width;
+    }
 
-    print_cell(series.total_episodes, Some(Color::Green), attribute);
-    print_separator(" | ", false);
+    if width > 0 {
+        truncated.push(ELLIPSIS);
+    }
 
-    print_cell(series.current_episode, Some(Color::Green), attribute);
+    truncated
 }
 
-fn print_cell<T: ToString>(cell: T, color: Option<Color>, style: Option<&str>) {
+fn print_cell(cell: &str, color: Option<Color>, style: &str) {
     if let Some(color) = color {
-        print!("{}", color);
+        draw!("{}", color);
     }
 
-    if let Some(style) = style {
-        print!("{}", style);
-    };
+    draw!("{style}{cell}{}", attribute::reset());
+}
+
+/// Underlines and highlights every case-insensitive occurrence of `highlight` in `cell`
+fn print_highlighted_cell(cell: &str, highlight: &str, color: Option<Color>, style: &str) {
+    // ASCII lowercasing keeps byte offsets intact, and matches what SQL's LIKE ignores
+    let haystack = cell.to_ascii_lowercase();
+    let needle = highlight.to_ascii_lowercase();
+
+    let mut start = 0;
+    for (match_start, _) in haystack.match_indices(&needle) {
+        if match_start < start {
+            continue;
+        }
+
+        let match_end = match_start + needle.len();
+        print_cell(&cell[start..match_start], color.clone(), style);
+        draw!("{}{}", attribute::underline(), HIGHLIGHT.background());
+        print_cell(&cell[match_start..match_end], color.clone(), style);
+
+        start = match_end;
+    }
 
-    print!("{}{}", cell.to_string(), attribute::reset());
+    print_cell(&cell[start..], color, style);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(names: &[&str]) -> Vec<Vec<String>> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let series = Series::new(name.to_string(), None, None, Some(28), Some(5), None);
+                cells(&series, idx)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn truncates_to_the_width() {
+        assert_eq!(truncate("Frieren", 7), "Frieren");
+        assert_eq!(truncate("Frieren", 5), "Frie…");
+        assert_eq!(truncate("Frieren", 1), "…");
+        assert_eq!(truncate("Frieren", 0), "");
+    }
+
+    #[test]
+    fn truncates_wide_characters_by_columns() {
+        // Every one of these takes two columns
+        assert_eq!(truncate("葬送のフリーレン", 16), "葬送のフリーレン");
+        assert_eq!(truncate("葬送のフリーレン", 5), "葬送…");
+        // Half a character doesn't fit, so the cut comes a column short
+        assert_eq!(truncate("葬送のフリーレン", 6), "葬送…");
+        assert_eq!(fit("葬送のフリーレン", 6, Align::Left).width(), 6);
+        assert_eq!(fit("葬送", 7, Align::Right), "   葬送");
+    }
+
+    #[test]
+    fn keeps_natural_widths_when_there_is_room() {
+        let rows = rows(&["Frieren", "葬送のフリーレン"]);
+        let widths = fit_widths(&rows, 500);
+
+        assert!(widths.iter().all(Option::is_some));
+        // Columns, not chars
+        assert_eq!(widths[NAME], Some(16));
+        assert_eq!(widths[1], Some(8));
+    }
+
+    #[test]
+    fn shrinks_and_drops_columns_to_fit() {
+        let rows = rows(&["Frieren", "葬送のフリーレン"]);
+
+        for width in [120, 80, 60, 40, 30] {
+            let widths = fit_widths(&rows, width);
+
+            assert!(table_width(&widths) <= width, "{width}: {widths:?}");
+            assert!(widths[0].is_some() && widths[NAME].is_some());
+            // Columns go from the right
+            let shown = widths.iter().take_while(|width| width.is_some()).count();
+            assert!(widths[shown..].iter().all(Option::is_none));
+        }
+
+        let widths = fit_widths(&rows, 40);
+        assert!(widths[NAME].unwrap() >= 8);
+        assert_eq!(widths.last(), Some(&None));
+    }
+
+    #[test]
+    fn gives_up_past_the_name() {
+        let rows = rows(&["葬送のフリーレン"]);
+        let widths = fit_widths(&rows, 5);
+
+        // Down to their minimums, wider than asked for
+        assert_eq!(widths[..=NAME], [Some(1), Some(8), Some(8)]);
+        assert!(widths[NAME + 1..].iter().all(Option::is_none));
+    }
 }
@@ -0,0 +1,228 @@
+use rusqlite::{Connection, Error};
+
+/// One season of a `Series`, numbered by its position starting at 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Season {
+    pub(crate) total_episodes: i32,
+    pub(crate) current_episode: i32,
+}
+
+impl Season {
+    fn is_done(&self) -> bool {
+        self.total_episodes > 0 && self.current_episode >= self.total_episodes
+    }
+}
+
+/// Replaces the stored seasons of a series, an empty list makes it single-season again
+pub(crate) fn save(conn: &Connection, series_guid: &str, seasons: &[Season]) -> Result<(), Error> {
+    conn.execute("DELETE FROM seasons WHERE series_guid = ?", [series_guid])?;
+
+    for (idx, season) in seasons.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO seasons (series_guid, number, total_episodes, current_episode) VALUES (?, ?, ?, ?)",
+            (series_guid, idx + 1, season.total_episodes, season.current_episode),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Watches forward through the first unfinished seasons, or backwards from the last started one.
+/// The last season takes whatever is left when its total is unknown.
+pub(crate) fn add_episodes(seasons: &mut [Season], qty: i32) {
+    let mut left = qty;
+
+    if left > 0 {
+        let count = seasons.len();
+        for (idx, season) in seasons.iter_mut().enumerate() {
+            let room = match season.total_episodes > 0 {
+                true => (season.total_episodes - season.current_episode).max(0),
+                false if idx + 1 == count => left,
+                false => 0,
+            };
+
+            let watched = left.min(room);
+            season.current_episode = season.current_episode.saturating_add(watched);
+            left -= watched;
+        }
+    } else {
+        for season in seasons.iter_mut().rev() {
+            let unwatched = (-left).min(season.current_episode);
+            season.current_episode -= unwatched;
+            left += unwatched;
+        }
+    }
+}
+
+/// Watched and total episodes over every season, the total is unknown (0) when any season's is
+pub(crate) fn totals(seasons: &[Season]) -> (i32, i32) {
+    let current = seasons.iter().map(|season| season.current_episode).sum();
+    let total = match seasons.iter().any(|season| season.total_episodes == 0) {
+        true => 0,
+        false => seasons.iter().map(|season| season.total_episodes).sum(),
+    };
+
+    (current, total)
+}
+
+/// The watched and total counts a new series starts with. Seasons replace both counts, so the
+/// guessed status has to be worked out from theirs
+pub(crate) fn counts(
+    seasons: &[Season],
+    current: Option<i32>,
+    total: Option<i32>,
+) -> (Option<i32>, Option<i32>) {
+    match seasons.is_empty() {
+        true => (current, total),
+        false => {
+            let (current, total) = totals(seasons);
+            (Some(current), Some(total))
+        }
+    }
+}
+
+/// "S02E05" for the first episode not watched yet, `None` once everything is
+pub(crate) fn next_episode(seasons: &[Season]) -> Option<String> {
+    let (idx, season) = seasons
+        .iter()
+        .enumerate()
+        .find(|(_, season)| !season.is_done())?;
+
+    Some(format!("S{:02}E{:02}", idx + 1, season.current_episode + 1))
+}
+
+/// "5/12, 0/24": watched and total episodes per season, how seasons are typed in and exported
+pub(crate) fn format(seasons: &[Season]) -> String {
+    seasons
+        .iter()
+        .map(|season| format!("{}/{}", season.current_episode, season.total_episodes))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Reads what `format` writes, a bare number is a season with nothing watched yet
+pub(crate) fn parse(value: &str) -> Result<Vec<Season>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|season| !season.is_empty())
+        .map(|season| {
+            let (current, total) = season.split_once('/').unwrap_or(("0", season));
+            let number = |value: &str| value.trim().parse::<i32>().ok().filter(|nr| *nr >= 0);
+
+            match (number(current), number(total)) {
+                (Some(current), Some(total)) if total == 0 || current <= total => Ok(Season {
+                    total_episodes: total,
+                    current_episode: current,
+                }),
+                _ => Err(format!("'{season}' is not a season like 5/12")),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seasons(progress: &[(i32, i32)]) -> Vec<Season> {
+        progress
+            .iter()
+            .map(|(current_episode, total_episodes)| Season {
+                total_episodes: *total_episodes,
+                current_episode: *current_episode,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn watches_forward_through_unfinished_seasons() {
+        let mut watched = seasons(&[(10, 12), (0, 12)]);
+
+        add_episodes(&mut watched, 5);
+        assert_eq!(watched, seasons(&[(12, 12), (3, 12)]));
+
+        // Stops at the end when every total is known
+        add_episodes(&mut watched, 100);
+        assert_eq!(watched, seasons(&[(12, 12), (12, 12)]));
+    }
+
+    #[test]
+    fn the_last_season_takes_the_rest_when_its_total_is_unknown() {
+        let mut watched = seasons(&[(12, 12), (0, 0)]);
+
+        add_episodes(&mut watched, 30);
+        assert_eq!(watched, seasons(&[(12, 12), (30, 0)]));
+
+        // An unknown total before the last one has no room
+        let mut watched = seasons(&[(0, 0), (0, 12)]);
+        add_episodes(&mut watched, 3);
+        assert_eq!(watched, seasons(&[(0, 0), (3, 12)]));
+
+        let mut watched = seasons(&[(12, 12), (1, 0)]);
+        add_episodes(&mut watched, i32::MAX);
+        assert_eq!(watched, seasons(&[(12, 12), (i32::MAX, 0)]));
+    }
+
+    #[test]
+    fn watches_backwards_from_the_last_started_season() {
+        let mut watched = seasons(&[(12, 12), (3, 12)]);
+
+        add_episodes(&mut watched, -5);
+        assert_eq!(watched, seasons(&[(10, 12), (0, 12)]));
+
+        // Stops at nothing watched
+        add_episodes(&mut watched, -100);
+        assert_eq!(watched, seasons(&[(0, 12), (0, 12)]));
+    }
+
+    #[test]
+    fn sums_the_totals() {
+        assert_eq!(totals(&seasons(&[(12, 12), (3, 24)])), (15, 36));
+        assert_eq!(totals(&seasons(&[(12, 12), (3, 0)])), (15, 0));
+        assert_eq!(totals(&[]), (0, 0));
+    }
+
+    #[test]
+    fn seasons_replace_the_counts() {
+        assert_eq!(counts(&[], Some(3), None), (Some(3), None));
+        assert_eq!(
+            counts(&seasons(&[(12, 12), (3, 24)]), Some(1), Some(2)),
+            (Some(15), Some(36))
+        );
+    }
+
+    #[test]
+    fn names_the_next_episode() {
+        assert_eq!(
+            next_episode(&seasons(&[(12, 12), (4, 12)])),
+            Some("S02E05".to_string())
+        );
+        assert_eq!(
+            next_episode(&seasons(&[(12, 12), (12, 0)])),
+            Some("S02E13".to_string())
+        );
+        assert_eq!(next_episode(&seasons(&[(12, 12), (12, 12)])), None);
+    }
+
+    #[test]
+    fn parses_what_it_formats() {
+        let watched = seasons(&[(12, 12), (5, 24), (0, 0)]);
+
+        assert_eq!(format(&watched), "12/12, 5/24, 0/0");
+        assert_eq!(parse(&format(&watched)), Ok(watched));
+        assert_eq!(parse(""), Ok(vec![]));
+        // A bare number is a season not started yet
+        assert_eq!(parse("12, 3/0"), Ok(seasons(&[(0, 12), (3, 0)])));
+    }
+
+    #[test]
+    fn refuses_progress_past_the_total() {
+        assert!(parse("13/12").is_err());
+        assert!(parse("12/12, 25/24").is_err());
+        assert!(parse("-1/12").is_err());
+        assert!(parse("5/twelve").is_err());
+        // Unless the total is unknown
+        assert_eq!(parse("13/0"), Ok(seasons(&[(13, 0)])));
+    }
+}
@@ -1,18 +1,298 @@
-use rusqlite::{Connection, Error};
+use rusqlite::{params, params_from_iter, types::Value, Connection, Error, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::db::get_connection;
+use crate::{
+    db::{self, get_connection},
+    history::WatchEvent,
+    season::{self, Season},
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WatchStatus {
+    Watching,
+    Completed,
+    Dropped,
+    PlanToWatch,
+}
+
+impl WatchStatus {
+    pub(crate) const ALL: [WatchStatus; 4] = [
+        WatchStatus::Watching,
+        WatchStatus::Completed,
+        WatchStatus::Dropped,
+        WatchStatus::PlanToWatch,
+    ];
+
+    /// How the status is stored in the database
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            WatchStatus::Watching => "watching",
+            WatchStatus::Completed => "completed",
+            WatchStatus::Dropped => "dropped",
+            WatchStatus::PlanToWatch => "plan_to_watch",
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            WatchStatus::Watching => "Watching",
+            WatchStatus::Completed => "Completed",
+            WatchStatus::Dropped => "Dropped",
+            WatchStatus::PlanToWatch => "Plan to watch",
+        }
+    }
+
+    /// Accepts the stored form, the label or any prefix of them, like "drop" or "plan"
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        if value.is_empty() {
+            return None;
+        }
+
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str().starts_with(&value))
+    }
+
+    /// Best guess for series that were not given a status explicitly
+    fn from_progress(is_finished: bool, current_episode: i32) -> Self {
+        if is_finished {
+            WatchStatus::Completed
+        } else if current_episode > 0 {
+            WatchStatus::Watching
+        } else {
+            WatchStatus::PlanToWatch
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum SortKey {
+    #[default]
+    Name,
+    Progress,
+    TotalEpisodes,
+    Finished,
+    DateAdded,
+    Rating,
+}
+
+impl SortKey {
+    const ALL: [SortKey; 6] = [
+        SortKey::Name,
+        SortKey::Progress,
+        SortKey::TotalEpisodes,
+        SortKey::Finished,
+        SortKey::DateAdded,
+        SortKey::Rating,
+    ];
+
+    pub(crate) fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|key| *key == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Progress => "progress",
+            SortKey::TotalEpisodes => "total episodes",
+            SortKey::Finished => "finished",
+            SortKey::DateAdded => "date added",
+            SortKey::Rating => "rating",
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Progress => {
+                "CASE WHEN total_episodes > 0 THEN CAST(current_episode AS REAL) / total_episodes ELSE 0 END"
+            }
+            SortKey::TotalEpisodes => "total_episodes",
+            SortKey::Finished => "is_finished",
+            SortKey::DateAdded => "added_at",
+            // Unrated sorts below a 1
+            SortKey::Rating => "COALESCE(rating, 0)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Sort {
+    pub(crate) key: SortKey,
+    pub(crate) descending: bool,
+}
+
+impl Sort {
+    const SETTING: &'static str = "sort";
+
+    pub(crate) fn load(conn: &Connection) -> Result<Self, Error> {
+        let Some(setting) = db::get_setting(conn, Self::SETTING)? else {
+            return Ok(Self::default());
+        };
+
+        let (key, direction) = setting.split_once(':').unwrap_or((&setting, "asc"));
+        let key = SortKey::ALL
+            .into_iter()
+            .find(|sort_key| sort_key.label() == key)
+            .unwrap_or_default();
+
+        Ok(Self {
+            key,
+            descending: direction == "desc",
+        })
+    }
+
+    pub(crate) fn save(&self, conn: &Connection) -> Result<(), Error> {
+        let direction = if self.descending { "desc" } else { "asc" };
+        db::set_setting(
+            conn,
+            Self::SETTING,
+            &format!("{}:{direction}", self.key.label()),
+        )
+    }
+
+    fn order_clause(&self) -> String {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+
+        // Name and guid keep rows with equal keys from swapping places between pages
+        format!(
+            "ORDER BY {} {direction}, name ASC, guid ASC",
+            self.key.column()
+        )
+    }
+}
+
+/// Whether a series needs every filtered tag or just one of them
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum TagMatch {
+    #[default]
+    All,
+    Any,
+}
+
+impl TagMatch {
+    pub(crate) fn toggle(self) -> Self {
+        match self {
+            TagMatch::All => TagMatch::Any,
+            TagMatch::Any => TagMatch::All,
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            TagMatch::All => "all",
+            TagMatch::Any => "any",
+        }
+    }
+}
+
+/// Narrows down which series `Series::get` and `Series::count` see, and in which order
+#[derive(Default)]
+pub(crate) struct Query {
+    pub(crate) search: Option<String>,
+    pub(crate) status: Option<WatchStatus>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) tag_match: TagMatch,
+    pub(crate) sort: Sort,
+}
+
+impl Query {
+    fn where_clause(&self) -> (String, Vec<Value>) {
+        let mut conditions = vec![];
+        let mut params = vec![];
+
+        if let Some(search) = self.search.as_ref().filter(|search| !search.is_empty()) {
+            // LIKE is case-insensitive for ASCII
+            conditions.push("name LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("%{}%", escape_like(search))));
+        }
+
+        if let Some(status) = self.status {
+            conditions.push("status = ?".to_string());
+            params.push(Value::Text(status.as_str().to_string()));
+        }
+
+        if !self.tags.is_empty() {
+            let placeholders = vec!["?"; self.tags.len()].join(", ");
+            // Tag names compare without case, the column is NOCASE
+            let having = match self.tag_match {
+                TagMatch::All => format!("HAVING COUNT(DISTINCT tag_id) = {}", self.tags.len()),
+                TagMatch::Any => String::new(),
+            };
+
+            conditions.push(format!(
+                r"guid IN (
+                    SELECT series_guid
+                    FROM series_tags
+                    JOIN tags ON tags.id = series_tags.tag_id
+                    WHERE tags.name IN ({placeholders})
+                    GROUP BY series_guid
+                    {having}
+                )"
+            ));
+            params.extend(self.tags.iter().cloned().map(Value::Text));
+        }
+
+        if conditions.is_empty() {
+            return (String::new(), params);
+        }
+
+        (format!("WHERE {}", conditions.join(" AND ")), params)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Series {
+    /// Imports may leave it out, they get a new one then
+    #[serde(default)]
     pub(crate) guid: String,
     pub(crate) name: String,
     pub(crate) is_finished: bool,
     pub(crate) is_airing_finished: bool,
     pub(crate) total_episodes: i32,
     pub(crate) current_episode: i32,
+    pub(crate) status: WatchStatus,
+    /// 1 to 10, `None` until rated
+    #[serde(default)]
+    pub(crate) rating: Option<u8>,
+    #[serde(default)]
+    pub(crate) notes: String,
+    /// Kept in `series_tags`, written as one comma-separated value so CSV can hold it too
+    #[serde(default, with = "comma_separated")]
+    pub(crate) tags: Vec<String>,
+    /// Empty for single-season series, otherwise the episode counts above are their sums
+    #[serde(default, with = "season_list")]
+    pub(crate) seasons: Vec<Season>,
+    /// UTC, as `YYYY-MM-DD HH:MM:SS`. Stamped by the database, `None` until first saved.
+    /// Exported so restoring a backup keeps them.
+    #[serde(default)]
+    pub(crate) added_at: Option<String>,
+    #[serde(default)]
+    pub(crate) updated_at: Option<String>,
 }
 
+/// Scores a series can be given, inclusive
+pub(crate) const RATING_RANGE: std::ops::RangeInclusive<u8> = 1..=10;
+
+const COLUMNS: &str = r"
+    guid, name, is_finished, is_airing_finished, total_episodes, current_episode, status, rating, notes,
+    (
+        SELECT group_concat(tags.name, ',')
+        FROM series_tags
+        JOIN tags ON tags.id = series_tags.tag_id
+        WHERE series_tags.series_guid = series.guid
+    ),
+    (
+        SELECT group_concat(current_episode || '/' || total_episodes, ',' ORDER BY number)
+        FROM seasons
+        WHERE seasons.series_guid = series.guid
+    ),
+    added_at, updated_at";
+
 impl Series {
     pub(crate) fn new(
         name: String,
@@ -20,28 +300,34 @@ impl Series {
         is_airing_finished: Option<bool>,
         total_episodes: Option<i32>,
         current_episode: Option<i32>,
+        status: Option<WatchStatus>,
     ) -> Self {
         let guid = Uuid::new_v4().to_string();
         let current_episode = current_episode.unwrap_or(0);
         let total_episodes = total_episodes.unwrap_or(0);
         let is_airing_finished = is_airing_finished.unwrap_or(false);
 
-        // debug_assert!(!name.is_empty(), "Name must not be empty");
-        // debug_assert!(
-        //     current_episode >= 0,
-        //     "Current episode must be a positive number"
-        // );
-        // debug_assert!(
-        //     total_episodes >= 0,
-        //     "Total episodes must be a positive number"
-        // );
-        // debug_assert!(
-        //     current_episode > total_episodes,
-        //     "Current episode cannot be higher than total"
-        // );
-
-        let is_finished =
-            is_finished.unwrap_or(is_airing_finished && current_episode == total_episodes);
+        // Callers validate their input first, these only catch the ones that forgot
+        debug_assert!(!name.trim().is_empty(), "Name must not be empty");
+        debug_assert!(
+            current_episode >= 0,
+            "Current episode must be a positive number"
+        );
+        debug_assert!(
+            total_episodes >= 0,
+            "Total episodes must be a positive number"
+        );
+        debug_assert!(
+            total_episodes == 0 || current_episode <= total_episodes,
+            "Current episode cannot be higher than total"
+        );
+
+        let is_finished = is_finished.unwrap_or(has_watched_everything(
+            is_airing_finished,
+            current_episode,
+            total_episodes,
+        ));
+        let status = status.unwrap_or(WatchStatus::from_progress(is_finished, current_episode));
 
         Self {
             guid,
@@ -50,111 +336,714 @@ impl Series {
             is_airing_finished,
             total_episodes,
             current_episode,
+            status,
+            rating: None,
+            notes: String::new(),
+            tags: vec![],
+            seasons: vec![],
+            added_at: None,
+            updated_at: None,
         }
     }
 
-    pub(crate) fn get(take: usize, skip: usize) -> Result<Vec<Self>, Error> {
-        let ctx = get_connection()?;
+    fn from_row(row: &Row) -> Result<Self, Error> {
+        let status: String = row.get(6)?;
+
+        Ok(Self {
+            guid: row.get(0)?,
+            name: row.get(1)?,
+            is_finished: row.get(2)?,
+            is_airing_finished: row.get(3)?,
+            total_episodes: row.get(4)?,
+            current_episode: row.get(5)?,
+            status: WatchStatus::parse(&status).unwrap_or(WatchStatus::PlanToWatch),
+            rating: row.get(7)?,
+            notes: row.get(8)?,
+            tags: parse_tags(&row.get::<_, Option<String>>(9)?.unwrap_or_default()),
+            seasons: season::parse(&row.get::<_, Option<String>>(10)?.unwrap_or_default())
+                .unwrap_or_default(),
+            added_at: row.get(11)?,
+            updated_at: row.get(12)?,
+        })
+    }
+
+    /// Moves the progress by `qty` episodes, staying within `[0, total_episodes]` when the total is known
+    pub(crate) fn add_episodes(&mut self, qty: i32) {
+        if self.seasons.is_empty() {
+            let mut current_episode = self.current_episode.saturating_add(qty).max(0);
+            if self.total_episodes > 0 {
+                current_episode = current_episode.min(self.total_episodes);
+            }
+
+            self.current_episode = current_episode;
+        } else {
+            season::add_episodes(&mut self.seasons, qty);
+            self.sync_seasons();
+        }
+
+        if has_watched_everything(
+            self.is_airing_finished,
+            self.current_episode,
+            self.total_episodes,
+        ) {
+            self.is_finished = true;
+            self.status = WatchStatus::Completed;
+        } else if qty < 0 && self.current_episode < self.total_episodes {
+            // Undoes the auto-finish above, like `WatchEvent::rollback_last` does
+            self.is_finished = false;
+            if self.status == WatchStatus::Completed {
+                self.status = WatchStatus::Watching;
+            }
+        } else if self.status == WatchStatus::PlanToWatch && self.current_episode > 0 {
+            self.status = WatchStatus::Watching;
+        }
+    }
+
+    /// Series whose name matches `name` once case and spacing are ignored, or is a typo or two away.
+    /// Sequels are told apart by their numbering, so "Attack on Titan 2" is not a typo of the first.
+    pub(crate) fn find_duplicates(conn: &Connection, name: &str) -> Result<Vec<Self>, Error> {
+        let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM series ORDER BY name"))?;
+        let mut rows = stmt.query([])?;
+
+        let mut duplicates = vec![];
+        while let Some(row) = rows.next()? {
+            let series = Self::from_row(row)?;
+            if is_duplicate_name(&series.name, name) {
+                duplicates.push(series);
+            }
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Derives the episode counts from the seasons, a season with an unknown total makes the whole total unknown
+    pub(crate) fn sync_seasons(&mut self) {
+        if self.seasons.is_empty() {
+            return;
+        }
+
+        (self.current_episode, self.total_episodes) = season::totals(&self.seasons);
+    }
+
+    /// The checks the form and the CLI run on their input, for series that come from elsewhere.
+    /// `Err` says what is wrong with the first bad field.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name can't be empty".to_string());
+        }
+
+        if let Some(rating) = self.rating.filter(|rating| !RATING_RANGE.contains(rating)) {
+            return Err(format!(
+                "rating {rating} is not from {} to {}",
+                RATING_RANGE.start(),
+                RATING_RANGE.end()
+            ));
+        }
+
+        for at in [&self.added_at, &self.updated_at].into_iter().flatten() {
+            if !is_timestamp(at) {
+                return Err(format!("'{at}' is not a time like 2024-01-31 18:00:00"));
+            }
+        }
+
+        // Seasons replace both counts and were checked when they were read
+        if !self.seasons.is_empty() {
+            return Ok(());
+        }
+
+        if self.current_episode < 0 || self.total_episodes < 0 {
+            return Err("episode counts can't be negative".to_string());
+        }
+
+        if self.total_episodes > 0 && self.current_episode > self.total_episodes {
+            return Err(format!(
+                "current episode {} is past the total of {}",
+                self.current_episode, self.total_episodes
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Like "S02E05", single-season series count as season 1
+    pub(crate) fn next_episode(&self) -> Option<String> {
+        if !self.seasons.is_empty() {
+            return season::next_episode(&self.seasons);
+        }
+
+        season::next_episode(&[Season {
+            total_episodes: self.total_episodes,
+            current_episode: self.current_episode,
+        }])
+    }
+
+    pub(crate) fn get(take: usize, skip: usize, query: &Query) -> Result<Vec<Self>, Error> {
+        Self::get_in(&get_connection()?, take, skip, query)
+    }
+
+    pub(crate) fn get_in(
+        conn: &Connection,
+        take: usize,
+        skip: usize,
+        query: &Query,
+    ) -> Result<Vec<Self>, Error> {
         let mut series = Vec::new();
 
-        let mut stmt = ctx.prepare(
+        let (where_clause, mut params) = query.where_clause();
+        params.push(Value::Integer(take as i64));
+        params.push(Value::Integer(skip as i64));
+
+        let order_clause = query.sort.order_clause();
+
+        let mut stmt = conn.prepare(&format!(
             r"
-            SELECT guid, name, is_finished, is_airing_finished, total_episodes, current_episode
+            SELECT {COLUMNS}
               FROM series
-            ORDER BY name
+            {where_clause}
+            {order_clause}
              LIMIT ?
             OFFSET ?
             ",
-        )?;
-        let mut rows = stmt.query([take, skip])?;
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params))?;
 
         while let Some(row) = rows.next()? {
-            series.push(Self {
-                guid: row.get(0)?,
-                name: row.get(1)?,
-                is_finished: row.get(2)?,
-                is_airing_finished: row.get(3)?,
-                total_episodes: row.get(4)?,
-                current_episode: row.get(5)?,
-            });
+            series.push(Self::from_row(row)?);
         }
 
         Ok(series)
     }
 
-    pub(crate) fn get_by_guid(guid: String) -> Result<Option<Self>, Error> {
-        let ctx = get_connection()?;
-        let mut stmt = ctx.prepare(
+    pub(crate) fn get_by_guid(conn: &Connection, guid: &str) -> Result<Option<Self>, Error> {
+        let mut stmt = conn.prepare(&format!(
             r#"
-            SELECT guid, name, is_finished, is_airing_finished, total_episodes, current_episode
+            SELECT {COLUMNS}
             FROM series
             WHERE guid = ?
             "#,
-        )?;
-        let mut rows = stmt.query([&guid])?;
+        ))?;
+        let mut rows = stmt.query([guid])?;
 
         match rows.next()? {
-            Some(row) => Ok(Some(Self {
-                guid: row.get(0)?,
-                name: row.get(1)?,
-                is_finished: row.get(2)?,
-                is_airing_finished: row.get(3)?,
-                total_episodes: row.get(4)?,
-                current_episode: row.get(5)?,
-            })),
+            Some(row) => Ok(Some(Self::from_row(row)?)),
             None => Ok(None),
         }
     }
 
-    pub(crate) fn try_insert(&mut self) -> Result<bool, Error> {
+    /// First series with this exact name, ignoring ASCII case
+    pub(crate) fn get_by_name(conn: &Connection, name: &str) -> Result<Option<Self>, Error> {
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT {COLUMNS}
+            FROM series
+            WHERE name = ? COLLATE NOCASE
+            ORDER BY added_at
+            LIMIT 1
+            "#,
+        ))?;
+        let mut rows = stmt.query([name])?;
+
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Looks a series up by guid, name or guid prefix, then by part of the name when
+    /// `by_part_of_name`. Exact matches win over partial ones, so more than one result means
+    /// the needle was ambiguous.
+    pub(crate) fn find(needle: &str, by_part_of_name: bool) -> Result<Vec<Self>, Error> {
         let ctx = get_connection()?;
-        let mut stmt = ctx.prepare(
+        let escaped = escape_like(needle);
+
+        let lookups = [
+            ("guid = ?", needle.to_string()),
+            ("name = ? COLLATE NOCASE", needle.to_string()),
+            ("guid LIKE ? ESCAPE '\\'", format!("{escaped}%")),
+            ("name LIKE ? ESCAPE '\\'", format!("%{escaped}%")),
+        ];
+        let lookups = match by_part_of_name {
+            true => &lookups[..],
+            false => &lookups[..3],
+        };
+
+        for (condition, param) in lookups {
+            let mut stmt = ctx.prepare(&format!(
+                "SELECT {COLUMNS} FROM series WHERE {condition} ORDER BY name"
+            ))?;
+            let mut rows = stmt.query([param])?;
+
+            let mut series = vec![];
+            while let Some(row) = rows.next()? {
+                series.push(Self::from_row(row)?);
+            }
+
+            if !series.is_empty() {
+                return Ok(series);
+            }
+        }
+
+        Ok(vec![])
+    }
+
+    /// Inserts unless the library already has a series with (nearly) the same name,
+    /// in which case nothing is written and those series come back instead
+    pub(crate) fn try_insert(&mut self, conn: &Connection) -> Result<Vec<Series>, Error> {
+        let duplicates = Self::find_duplicates(conn, &self.name)?;
+        if duplicates.is_empty() {
+            self.insert(conn)?;
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Inserts without looking for duplicates, like when restoring or importing.
+    /// Timestamps the series already has are kept, a new one is added now.
+    pub(crate) fn insert(&mut self, conn: &Connection) -> Result<bool, Error> {
+        self.sync_seasons();
+
+        let mut stmt = conn.prepare(
             r#"
-            INSERT INTO series (guid, name, is_finished, is_airing_finished, total_episodes, current_episode)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO series (guid, name, is_finished, is_airing_finished, total_episodes, current_episode, status, rating, notes, added_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now')), ?)
             "#)?;
 
-        stmt.execute([
-            &self.guid,
-            &self.name,
-            &(if self.is_finished { "1" } else { "0" }).to_string(),
-            &(if self.is_airing_finished { "1" } else { "0" }).to_string(),
-            &self.total_episodes.to_string(),
-            &self.current_episode.to_string(),
+        stmt.execute(params![
+            self.guid,
+            self.name,
+            self.is_finished,
+            self.is_airing_finished,
+            self.total_episodes,
+            self.current_episode,
+            self.status.as_str(),
+            self.rating,
+            self.notes,
+            self.added_at,
+            self.updated_at,
         ])?;
+        self.save_tags(conn)?;
+        season::save(conn, &self.guid, &self.seasons)?;
 
         Ok(true)
     }
 
-    pub(crate) fn try_update(&mut self) -> Result<bool, Error> {
-        let ctx = get_connection()?;
-        let mut stmt = ctx.prepare(
+    /// Saves every field, and logs a `WatchEvent` when the episode progress moved
+    pub(crate) fn try_update(&mut self, conn: &Connection) -> Result<bool, Error> {
+        let old_episode: Option<i32> = conn
+            .query_row(
+                "SELECT current_episode FROM series WHERE guid = ?",
+                [&self.guid],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        self.try_update_quietly(conn)?;
+
+        match old_episode {
+            Some(old_episode) if old_episode != self.current_episode => {
+                WatchEvent::record(conn, &self.guid, old_episode, self.current_episode)?;
+            }
+            _ => (),
+        }
+
+        Ok(true)
+    }
+
+    /// Like `try_update`, but leaves the watch history alone, for imports
+    pub(crate) fn try_update_quietly(&mut self, conn: &Connection) -> Result<bool, Error> {
+        self.sync_seasons();
+
+        let mut stmt = conn.prepare(
             r#"
             UPDATE series
-            SET name = ?, is_finished = ?, is_airing_finished = ?, total_episodes = ?, current_episode = ?
+            SET name = ?, is_finished = ?, is_airing_finished = ?, total_episodes = ?, current_episode = ?, status = ?, rating = ?, notes = ?, updated_at = datetime('now')
             WHERE guid = ?
             "#)?;
-        stmt.execute([
-            &self.name,
-            &(if self.is_finished { "1" } else { "0" }).to_string(),
-            &(if self.is_airing_finished { "1" } else { "0" }).to_string(),
-            &self.total_episodes.to_string(),
-            &self.current_episode.to_string(),
-            &self.guid,
+        stmt.execute(params![
+            self.name,
+            self.is_finished,
+            self.is_airing_finished,
+            self.total_episodes,
+            self.current_episode,
+            self.status.as_str(),
+            self.rating,
+            self.notes,
+            self.guid,
         ])?;
 
+        self.save_tags(conn)?;
+        season::save(conn, &self.guid, &self.seasons)?;
+
         Ok(true)
     }
 
+    /// `add_episodes` followed by `try_update`
+    pub(crate) fn try_watch(&mut self, conn: &Connection, qty: i32) -> Result<bool, Error> {
+        self.add_episodes(qty);
+        self.try_update(conn)
+    }
+
+    /// Replaces the stored tags with `self.tags`, creating the ones never used before
+    fn save_tags(&self, conn: &Connection) -> Result<(), Error> {
+        conn.execute(
+            "DELETE FROM series_tags WHERE series_guid = ?",
+            [&self.guid],
+        )?;
+
+        for tag in &self.tags {
+            conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?)", [tag])?;
+            conn.execute(
+                "INSERT OR IGNORE INTO series_tags (series_guid, tag_id) SELECT ?, id FROM tags WHERE name = ?",
+                [&self.guid, tag],
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn try_delete(&mut self, conn: &Connection) -> Result<bool, Error> {
         let mut stmt = conn.prepare("DELETE FROM series WHERE guid = ?")?;
         stmt.execute([&self.guid])?;
+        // Undoing the delete writes them back from `self.tags` and `self.seasons`
+        conn.execute(
+            "DELETE FROM series_tags WHERE series_guid = ?",
+            [&self.guid],
+        )?;
+        season::save(conn, &self.guid, &[])?;
 
         Ok(true)
     }
 
     pub(crate) fn count_total() -> Result<usize, Error> {
+        Self::count(&Query::default())
+    }
+
+    /// Counts per status, ignoring the status the query is narrowed to so every tab gets a number
+    pub(crate) fn count_by_status(query: &Query) -> Result<Vec<(WatchStatus, usize)>, Error> {
         let ctx = get_connection()?;
-        ctx.query_row("SELECT COUNT(guid) FROM series", [], |row| row.get(0))
+        let query = Query {
+            search: query.search.clone(),
+            status: None,
+            tags: query.tags.clone(),
+            tag_match: query.tag_match,
+            sort: query.sort,
+        };
+        let (where_clause, params) = query.where_clause();
+
+        let mut stmt = ctx.prepare(&format!(
+            "SELECT status, COUNT(guid) FROM series {where_clause} GROUP BY status"
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params))?;
+
+        let mut counts: Vec<(WatchStatus, usize)> =
+            WatchStatus::ALL.iter().map(|status| (*status, 0)).collect();
+
+        while let Some(row) = rows.next()? {
+            let status: String = row.get(0)?;
+            let Some(status) = WatchStatus::parse(&status) else {
+                continue;
+            };
+
+            if let Some(count) = counts.iter_mut().find(|(known, _)| *known == status) {
+                count.1 = row.get(1)?;
+            }
+        }
+
+        Ok(counts)
+    }
+
+    pub(crate) fn count(query: &Query) -> Result<usize, Error> {
+        Self::count_in(&get_connection()?, query)
+    }
+
+    pub(crate) fn count_in(conn: &Connection, query: &Query) -> Result<usize, Error> {
+        let (where_clause, params) = query.where_clause();
+
+        conn.query_row(
+            &format!("SELECT COUNT(guid) FROM series {where_clause}"),
+            params_from_iter(params),
+            |row| row.get(0),
+        )
+    }
+}
+
+fn has_watched_everything(
+    is_airing_finished: bool,
+    current_episode: i32,
+    total_episodes: i32,
+) -> bool {
+    is_airing_finished && current_episode == total_episodes
+}
+
+/// "  Sousou no  FRIEREN " and "sousou no frieren" are the same name
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn is_duplicate_name(a: &str, b: &str) -> bool {
+    let (a, b) = (normalize_name(a), normalize_name(b));
+    if a == b {
+        return true;
+    }
+
+    let ((a, a_sequel), (b, b_sequel)) = (split_sequel(&a), split_sequel(&b));
+    if a_sequel != b_sequel {
+        return false;
+    }
+
+    // Short names are too close to each other for typos to mean anything
+    let max_distance = match a.chars().count().min(b.chars().count()) {
+        0..=3 => 0,
+        len => (len / 3).min(3),
+    };
+
+    edit_distance(a, b) <= max_distance
+}
+
+/// Splits a normalized name into its title and the words numbering a sequel after it,
+/// like "2", "ii", "season 3", "s2", "2nd season" or "part 2"
+fn split_sequel(name: &str) -> (&str, &str) {
+    let is_marker = |word: &str| {
+        let is_number = |word: &str| !word.is_empty() && word.chars().all(|c| c.is_ascii_digit());
+        let is_roman = word.len() <= 4 && word.chars().all(|c| matches!(c, 'i' | 'v' | 'x'));
+        let is_ordinal = ["st", "nd", "rd", "th"]
+            .iter()
+            .any(|suffix| word.strip_suffix(suffix).is_some_and(is_number));
+
+        is_number(word)
+            || is_roman
+            || is_ordinal
+            || matches!(word, "season" | "part" | "cour")
+            || word.strip_prefix('s').is_some_and(is_number)
+    };
+
+    let mut title_end = name.len();
+    while let Some((rest, word)) = name[..title_end].trim_end().rsplit_once(' ') {
+        if !is_marker(word) {
+            break;
+        }
+        title_end = rest.len();
+    }
+
+    (name[..title_end].trim_end(), name[title_end..].trim())
+}
+
+/// Levenshtein distance, counted in chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Shaped like the `YYYY-MM-DD HH:MM:SS` SQLite's `datetime` writes, so dates sort as text
+fn is_timestamp(value: &str) -> bool {
+    value.len() == 19
+        && value.chars().enumerate().all(|(idx, c)| match idx {
+            4 | 7 => c == '-',
+            10 => c == ' ',
+            13 | 16 => c == ':',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// Escapes LIKE's wildcards so they match literally, pair with `ESCAPE '\'`
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Splits "Drama, by Alex,drama" into `["Drama", "by Alex"]`, dropping blanks and repeats
+pub(crate) fn parse_tags(value: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+
+    for tag in value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+    {
+        if !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    tags
+}
+
+mod comma_separated {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        tags: &[String],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&tags.join(", "))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        Ok(super::parse_tags(&String::deserialize(deserializer)?))
+    }
+}
+
+mod season_list {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::season::{self, Season};
+
+    pub(super) fn serialize<S: Serializer>(
+        seasons: &[Season],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&season::format(seasons))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Season>, D::Error> {
+        season::parse(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    #[test]
+    fn measures_edit_distance_in_chars() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("frieren", "frieren"), 0);
+        assert_eq!(edit_distance("frieren", "freiren"), 2);
+        assert_eq!(edit_distance("frieren", "frieen"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("葬送のフリーレン", "葬送のフリレン"), 1);
+    }
+
+    #[test]
+    fn splits_off_sequel_numbering() {
+        assert_eq!(split_sequel("attack on titan"), ("attack on titan", ""));
+        assert_eq!(split_sequel("attack on titan 2"), ("attack on titan", "2"));
+        assert_eq!(split_sequel("mob psycho 100 ii"), ("mob psycho", "100 ii"));
+        assert_eq!(
+            split_sequel("kusuriya no hitorigoto 2nd season"),
+            ("kusuriya no hitorigoto", "2nd season")
+        );
+        assert_eq!(
+            split_sequel("jujutsu kaisen season 2 part 1"),
+            ("jujutsu kaisen", "season 2 part 1")
+        );
+        assert_eq!(split_sequel("86"), ("86", ""));
+    }
+
+    #[test]
+    fn finds_duplicates_but_not_sequels() {
+        let conn = db::open_in_memory();
+        for name in ["Attack on Titan", "Mob Psycho 100", "Bocchi the Rock!"] {
+            Series::new(name.to_string(), None, None, None, None, None)
+                .insert(&conn)
+                .unwrap();
+        }
+        let duplicates = |name: &str| {
+            Series::find_duplicates(&conn, name)
+                .unwrap()
+                .into_iter()
+                .map(|series| series.name)
+                .collect::<Vec<_>>()
+        };
+
+        // Only case and spacing apart
+        assert_eq!(duplicates("attack on TITAN"), vec!["Attack on Titan"]);
+        assert_eq!(duplicates("  Attack   on Titan "), vec!["Attack on Titan"]);
+        // Typos
+        assert_eq!(duplicates("Atack on Titan"), vec!["Attack on Titan"]);
+        assert_eq!(duplicates("Boochi the Rock!"), vec!["Bocchi the Rock!"]);
+        // Sequels
+        assert!(duplicates("Attack on Titan 2").is_empty());
+        assert!(duplicates("Attack on Titan Season 3").is_empty());
+        assert!(duplicates("Mob Psycho 100 II").is_empty());
+        assert!(duplicates("Mob Psycho 101").is_empty());
+        // Typos in a sequel still count once it's in the library
+        Series::new(
+            "Attack on Titan 2".to_string(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .insert(&conn)
+        .unwrap();
+        assert_eq!(duplicates("Atack on Titan 2"), vec!["Attack on Titan 2"]);
+        // Too short for typos
+        assert!(duplicates("Attack").is_empty());
+    }
+
+    #[test]
+    fn stepping_back_unfinishes() {
+        let mut series = Series::new(
+            "Frieren".to_string(),
+            None,
+            Some(true),
+            Some(28),
+            None,
+            None,
+        );
+
+        series.add_episodes(28);
+        assert!(series.is_finished);
+        assert_eq!(series.status, WatchStatus::Completed);
+
+        series.add_episodes(-1);
+        assert_eq!(series.current_episode, 27);
+        assert!(!series.is_finished);
+        assert_eq!(series.status, WatchStatus::Watching);
+
+        // Other statuses are left alone
+        series.status = WatchStatus::Dropped;
+        series.add_episodes(-1);
+        assert_eq!(series.status, WatchStatus::Dropped);
+    }
+
+    #[test]
+    fn restores_deleted_series_as_they_were() {
+        let conn = db::open_in_memory();
+        let mut series = Series::new("Frieren".to_string(), None, None, Some(28), None, None);
+        series.tags = vec!["fantasy".to_string()];
+        series.insert(&conn).unwrap();
+        series.try_watch(&conn, 3).unwrap();
+        conn.execute("UPDATE series SET added_at = '2020-01-01 00:00:00'", [])
+            .unwrap();
+
+        let mut deleted = Series::get_by_guid(&conn, &series.guid).unwrap().unwrap();
+        deleted.try_delete(&conn).unwrap();
+        assert!(Series::get_by_guid(&conn, &series.guid).unwrap().is_none());
+
+        deleted.insert(&conn).unwrap();
+        let restored = Series::get_by_guid(&conn, &series.guid).unwrap().unwrap();
+
+        assert_eq!(restored.added_at.as_deref(), Some("2020-01-01 00:00:00"));
+        assert!(restored.updated_at.is_some());
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&deleted).unwrap()
+        );
     }
 }
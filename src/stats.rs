@@ -0,0 +1,92 @@
+use rusqlite::{Connection, Error};
+
+/// How many weeks the per-week chart goes back, the current one included
+pub(crate) const WEEKS: usize = 8;
+
+pub(crate) struct Stats {
+    pub(crate) total_series: usize,
+    pub(crate) finished: usize,
+    /// Started but not finished
+    pub(crate) in_progress: usize,
+    pub(crate) episodes_watched: i64,
+    /// Over the series with a known episode count, `None` when there are none
+    pub(crate) average_completion: Option<f64>,
+    /// Monday of the week and the episodes watched in it, oldest first
+    pub(crate) weekly: Vec<(String, i64)>,
+    /// Whether any `WatchEvent` was ever recorded, the weekly numbers mean nothing without
+    pub(crate) has_history: bool,
+}
+
+impl Stats {
+    pub(crate) fn load(conn: &Connection) -> Result<Self, Error> {
+        let (total_series, finished, in_progress, episodes_watched, average_completion) = conn
+            .query_row(
+                r"
+                SELECT
+                    COUNT(*),
+                    COUNT(*) FILTER (WHERE is_finished = 1),
+                    COUNT(*) FILTER (WHERE is_finished = 0 AND current_episode > 0),
+                    COALESCE(SUM(current_episode), 0),
+                    AVG(MIN(current_episode * 100.0 / total_episodes, 100.0))
+                        FILTER (WHERE total_episodes > 0)
+                FROM series
+                ",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )?;
+
+        let has_history =
+            conn.query_row("SELECT EXISTS (SELECT 1 FROM watch_events)", [], |row| {
+                row.get(0)
+            })?;
+
+        Ok(Self {
+            total_series,
+            finished,
+            in_progress,
+            episodes_watched,
+            average_completion,
+            weekly: weekly(conn)?,
+            has_history,
+        })
+    }
+}
+
+// Rewinds only count as negative progress, not as episodes watched.
+// Weeks start on Monday, `weekday 0` lands on the coming Sunday (or today when it is one).
+fn weekly(conn: &Connection) -> Result<Vec<(String, i64)>, Error> {
+    let mut stmt = conn.prepare(
+        r"
+        WITH RECURSIVE weeks (start, n) AS (
+            SELECT date('now', 'weekday 0', '-6 days'), 1
+            UNION ALL
+            SELECT date(start, '-7 days'), n + 1 FROM weeks WHERE n < ?
+        )
+        SELECT
+            start,
+            (
+                SELECT COALESCE(SUM(MAX(new_episode - old_episode, 0)), 0)
+                FROM watch_events
+                WHERE watched_at >= start AND watched_at < date(start, '+7 days')
+            )
+        FROM weeks
+        ORDER BY start
+        ",
+    )?;
+    let mut rows = stmt.query([WEEKS])?;
+
+    let mut weeks = vec![];
+    while let Some(row) = rows.next()? {
+        weeks.push((row.get(0)?, row.get(1)?));
+    }
+
+    Ok(weeks)
+}
@@ -4,6 +4,10 @@ pub(crate) fn invert() -> String {
     format!("{CSI}7m")
 }
 
+pub(crate) fn underline() -> String {
+    format!("{CSI}4m")
+}
+
 pub(crate) fn reset() -> String {
     format!("{CSI}0m")
 }
@@ -1,9 +1,82 @@
 use crate::term::CSI;
-use std::fmt::Display;
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
 
 pub(crate) const FOREGROUND: i32 = 38;
 pub(crate) const BACKGROUND: i32 = 48;
 
+static SUPPORT: OnceLock<Support> = OnceLock::new();
+
+/// What `--color` asked for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(ColorMode::Auto),
+            "always" => Some(ColorMode::Always),
+            "never" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+}
+
+/// How many colors the terminal can show, from worst to best
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum Support {
+    None,
+    Basic,
+    Indexed,
+    TrueColor,
+}
+
+/// Settles which colors get written for the rest of the run
+pub(crate) fn init(mode: ColorMode) {
+    SUPPORT.get_or_init(|| detect(mode));
+}
+
+fn support() -> Support {
+    *SUPPORT.get_or_init(|| detect(ColorMode::Auto))
+}
+
+// https://no-color.org, then whatever the terminal advertises
+fn detect(mode: ColorMode) -> Support {
+    let colorterm = env::var("COLORTERM").unwrap_or_default();
+    let term = env::var("TERM").unwrap_or_default();
+    let depth = depth(&colorterm, &term);
+
+    match mode {
+        ColorMode::Never => Support::None,
+        ColorMode::Always if depth == Support::None => Support::Basic,
+        ColorMode::Always => depth,
+        ColorMode::Auto if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => {
+            Support::None
+        }
+        ColorMode::Auto if !io::stdout().is_terminal() => Support::None,
+        ColorMode::Auto => depth,
+    }
+}
+
+fn depth(colorterm: &str, term: &str) -> Support {
+    match (colorterm, term) {
+        ("truecolor" | "24bit", _) => Support::TrueColor,
+        (_, "" | "dumb") => Support::None,
+        (_, term) if term.ends_with("-direct") => Support::TrueColor,
+        (_, term) if term.contains("256color") => Support::Indexed,
+        _ => Support::Basic,
+    }
+}
+
+#[derive(Clone)]
 pub(crate) enum Color {
     Rgb(u8, u8, u8),
     Red,
@@ -12,24 +85,158 @@ pub(crate) enum Color {
     Magenta,
 }
 
+impl Color {
+    pub(crate) fn background(&self) -> String {
+        self.sequence(BACKGROUND)
+    }
+
+    fn rgb(&self) -> Rgb {
+        let (red, green, blue) = match self {
+            Color::Rgb(r, g, b) => (*r, *g, *b),
+            Color::Red => (255, 0, 0),
+            Color::Green => (0, 255, 0),
+            Color::Blue => (0, 0, 255),
+            Color::Magenta => (170, 0, 170),
+        };
+
+        Rgb { red, green, blue }
+    }
+
+    /// Empty when colors are off, `layer` is `FOREGROUND` or `BACKGROUND`
+    fn sequence(&self, layer: i32) -> String {
+        let rgb = self.rgb();
+
+        match support() {
+            Support::None => String::new(),
+            Support::Basic => {
+                // 30-37 and 40-47 for the normal colors, 90-97 and 100-107 for the bright ones
+                let idx = rgb.to_16() as i32;
+                let code = match idx {
+                    0..=7 => layer - 8 + idx,
+                    _ => layer + 52 + idx - 8,
+                };
+                format!("{CSI}{code}m")
+            }
+            Support::Indexed => format!("{CSI}{layer};5;{}m", rgb.to_256()),
+            Support::TrueColor => format!("{CSI}{layer};2;{};{};{}m", rgb.red, rgb.green, rgb.blue),
+        }
+    }
+}
+
 impl Display for Color {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{CSI}{FOREGROUND};{}m",
-            match self {
-                Color::Rgb(r, g, b) => format!("{};{};{}", r, g, b),
-                Color::Red => "255;0;0".to_string(),
-                Color::Green => "0;255;0".to_string(),
-                Color::Blue => "0;0;255".to_string(),
-                Color::Magenta => "170;0;170".to_string(),
-            }
-        )
+        f.write_str(&self.sequence(FOREGROUND))
     }
 }
 
+#[derive(Clone, Copy)]
 pub(crate) struct Rgb {
     red: u8,
     green: u8,
     blue: u8,
 }
+
+impl Rgb {
+    const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue }
+    }
+
+    fn distance(self, other: Rgb) -> i32 {
+        let diff = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        diff(self.red, other.red) + diff(self.green, other.green) + diff(self.blue, other.blue)
+    }
+
+    /// Nearest of the 6x6x6 cube and the grey ramp in the 256-color palette
+    fn to_256(self) -> u8 {
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        let level = |value: u8| {
+            (0..LEVELS.len())
+                .min_by_key(|idx| (LEVELS[*idx] as i32 - value as i32).abs())
+                .unwrap_or(0)
+        };
+
+        let (r, g, b) = (level(self.red), level(self.green), level(self.blue));
+        let cube = Rgb::new(LEVELS[r], LEVELS[g], LEVELS[b]);
+
+        // The ramp goes from 8 to 238 in steps of 10
+        let average = (self.red as i32 + self.green as i32 + self.blue as i32) / 3;
+        let step = ((average - 3) / 10).clamp(0, 23);
+        let grey_level = (8 + step * 10) as u8;
+        let grey = Rgb::new(grey_level, grey_level, grey_level);
+
+        match self.distance(grey) < self.distance(cube) {
+            true => 232 + step as u8,
+            false => (16 + 36 * r + 6 * g + b) as u8,
+        }
+    }
+
+    /// Nearest of the 16 basic colors, as xterm shows them by default
+    fn to_16(self) -> u8 {
+        const PALETTE: [Rgb; 16] = [
+            Rgb::new(0, 0, 0),
+            Rgb::new(205, 0, 0),
+            Rgb::new(0, 205, 0),
+            Rgb::new(205, 205, 0),
+            Rgb::new(0, 0, 238),
+            Rgb::new(205, 0, 205),
+            Rgb::new(0, 205, 205),
+            Rgb::new(229, 229, 229),
+            Rgb::new(127, 127, 127),
+            Rgb::new(255, 0, 0),
+            Rgb::new(0, 255, 0),
+            Rgb::new(255, 255, 0),
+            Rgb::new(92, 92, 255),
+            Rgb::new(255, 0, 255),
+            Rgb::new(0, 255, 255),
+            Rgb::new(255, 255, 255),
+        ];
+
+        (0..PALETTE.len())
+            .min_by_key(|idx| self.distance(PALETTE[*idx]))
+            .unwrap_or(0) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_depth_from_the_environment() {
+        assert_eq!(depth("truecolor", "xterm"), Support::TrueColor);
+        assert_eq!(depth("24bit", ""), Support::TrueColor);
+        assert_eq!(depth("", "xterm-direct"), Support::TrueColor);
+        assert_eq!(depth("", "xterm-256color"), Support::Indexed);
+        assert_eq!(depth("", "screen.xterm-256color"), Support::Indexed);
+        assert_eq!(depth("", "xterm"), Support::Basic);
+        assert_eq!(depth("", "linux"), Support::Basic);
+        assert_eq!(depth("", "dumb"), Support::None);
+        assert_eq!(depth("", ""), Support::None);
+        // Anything else in $COLORTERM says nothing about the depth
+        assert_eq!(depth("yes", "xterm-256color"), Support::Indexed);
+    }
+
+    #[test]
+    fn maps_to_the_256_color_palette() {
+        assert_eq!(Rgb::new(255, 0, 0).to_256(), 196);
+        assert_eq!(Rgb::new(0, 0, 0).to_256(), 16);
+        assert_eq!(Rgb::new(255, 255, 255).to_256(), 231);
+        // Exactly on the cube
+        assert_eq!(Rgb::new(135, 135, 0).to_256(), 100);
+        assert_eq!(Rgb::new(170, 0, 170).to_256(), 127);
+        // Greys closer to the ramp than to the cube
+        assert_eq!(Rgb::new(128, 128, 128).to_256(), 244);
+        assert_eq!(Rgb::new(190, 190, 190).to_256(), 250);
+    }
+
+    #[test]
+    fn maps_to_the_16_basic_colors() {
+        assert_eq!(Rgb::new(0, 0, 0).to_16(), 0);
+        assert_eq!(Rgb::new(205, 0, 0).to_16(), 1);
+        assert_eq!(Rgb::new(255, 0, 0).to_16(), 9);
+        assert_eq!(Rgb::new(170, 0, 170).to_16(), 5);
+        assert_eq!(Rgb::new(190, 190, 190).to_16(), 7);
+        // The search highlight has to stay visible
+        assert_eq!(Rgb::new(135, 135, 0).to_16(), 3);
+    }
+}
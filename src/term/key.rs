@@ -0,0 +1,299 @@
+use std::io;
+
+use libc::{poll, pollfd, read, POLLIN, STDIN_FILENO};
+
+use super::resize;
+
+// How long to wait for the rest of an escape sequence before treating ESC as a key press
+const ESCAPE_TIMEOUT_MS: i32 = 25;
+
+// Chords made of more than one action key, a prefix of one of these waits for the next key
+const CHORDS: &[&str] = &["gg", "dd"];
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Delete,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// Not a key, the terminal was resized while waiting for one
+    Resize,
+    Unknown,
+}
+
+impl Key {
+    /// Vim-like name of the key, used in chords and to match keybindings
+    pub(crate) fn notation(&self) -> String {
+        match self {
+            Key::Char(c) => c.to_string(),
+            Key::Ctrl(c) => format!("<C-{c}>"),
+            Key::Enter => "<CR>".to_string(),
+            Key::Tab => "<Tab>".to_string(),
+            Key::BackTab => "<S-Tab>".to_string(),
+            Key::Backspace => "<BS>".to_string(),
+            Key::Delete => "<Del>".to_string(),
+            Key::Esc => "<Esc>".to_string(),
+            Key::Up => "<Up>".to_string(),
+            Key::Down => "<Down>".to_string(),
+            Key::Left => "<Left>".to_string(),
+            Key::Right => "<Right>".to_string(),
+            Key::Home => "<Home>".to_string(),
+            Key::End => "<End>".to_string(),
+            Key::PageUp => "<PageUp>".to_string(),
+            Key::PageDown => "<PageDown>".to_string(),
+            Key::Resize | Key::Unknown => String::new(),
+        }
+    }
+}
+
+/// Blocks until one key press is available on stdin, or the terminal is resized
+pub(crate) fn read_key() -> io::Result<Key> {
+    match read_key_bytes() {
+        Err(er) if er.kind() == io::ErrorKind::Interrupted => Ok(Key::Resize),
+        key => key,
+    }
+}
+
+fn read_key_bytes() -> io::Result<Key> {
+    let byte = read_byte()?;
+
+    let key = match byte {
+        0x1B => read_escape()?,
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7F | 0x08 => Key::Backspace,
+        0x01..=0x1A => Key::Ctrl((b'a' + byte - 1) as char),
+        _ => Key::Char(read_utf8(byte)?),
+    };
+
+    Ok(key)
+}
+
+fn read_escape() -> io::Result<Key> {
+    if !has_pending_input(ESCAPE_TIMEOUT_MS) {
+        return Ok(Key::Esc);
+    }
+
+    let kind = read_byte()?;
+    if kind != b'[' && kind != b'O' {
+        // Alt+key, not something we bind
+        return Ok(Key::Unknown);
+    }
+
+    let mut params = String::new();
+    let final_byte = loop {
+        if !has_pending_input(ESCAPE_TIMEOUT_MS) {
+            return Ok(Key::Unknown);
+        }
+
+        match read_byte()? {
+            byte @ 0x40..=0x7E => break byte,
+            byte => params.push(byte as char),
+        }
+    };
+
+    let key = match final_byte {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        b'Z' => Key::BackTab,
+        b'~' => match params.split(';').next() {
+            Some("1") | Some("7") => Key::Home,
+            Some("4") | Some("8") => Key::End,
+            Some("3") => Key::Delete,
+            Some("5") => Key::PageUp,
+            Some("6") => Key::PageDown,
+            _ => Key::Unknown,
+        },
+        _ => Key::Unknown,
+    };
+
+    Ok(key)
+}
+
+fn read_utf8(first: u8) -> io::Result<char> {
+    let len = match first {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return Ok(char::REPLACEMENT_CHARACTER),
+    };
+
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        bytes.push(read_byte()?);
+    }
+
+    Ok(String::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.chars().next())
+        .unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+fn read_byte() -> io::Result<u8> {
+    let mut byte = 0u8;
+
+    loop {
+        let r = unsafe { read(STDIN_FILENO, &mut byte as *mut u8 as *mut _, 1) };
+
+        match r {
+            1 => return Ok(byte),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => {
+                let er = io::Error::last_os_error();
+                if er.kind() != io::ErrorKind::Interrupted || resize::is_pending() {
+                    return Err(er);
+                }
+            }
+        }
+    }
+}
+
+fn has_pending_input(timeout_ms: i32) -> bool {
+    let mut fds = pollfd {
+        fd: STDIN_FILENO,
+        events: POLLIN,
+        revents: 0,
+    };
+
+    unsafe { poll(&mut fds, 1, timeout_ms) > 0 }
+}
+
+/// Collects key presses until they form a whole keybinding, like `12j`, `gg` or `3dd`
+#[derive(Default)]
+pub(crate) struct Chord {
+    keys: String,
+}
+
+impl Chord {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.keys
+    }
+
+    /// Feeds one key, returns the finished chord once no longer chord can start with it
+    pub(crate) fn push(&mut self, key: Key) -> Option<String> {
+        match key {
+            Key::Unknown => None,
+            Key::Backspace if !self.keys.is_empty() => {
+                self.keys.pop();
+                None
+            }
+            Key::Esc if !self.keys.is_empty() => {
+                self.keys.clear();
+                None
+            }
+            Key::Char(c) if c.is_ascii_digit() => {
+                self.keys.push(c);
+                None
+            }
+            Key::Char(c) => {
+                self.keys.push(c);
+
+                let actions = self.keys.trim_start_matches(|c: char| c.is_ascii_digit());
+                let is_pending = CHORDS
+                    .iter()
+                    .any(|chord| chord.len() > actions.len() && chord.starts_with(actions));
+
+                if is_pending {
+                    None
+                } else {
+                    Some(std::mem::take(&mut self.keys))
+                }
+            }
+            key => {
+                self.keys.push_str(&key.notation());
+                Some(std::mem::take(&mut self.keys))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds every key, keeping the chords they finished along the way
+    fn feed(chord: &mut Chord, keys: Vec<Key>) -> Vec<String> {
+        keys.into_iter().filter_map(|key| chord.push(key)).collect()
+    }
+
+    fn chars(keys: &str) -> Vec<Key> {
+        keys.chars().map(Key::Char).collect()
+    }
+
+    #[test]
+    fn single_keys_finish_at_once() {
+        let mut chord = Chord::default();
+
+        assert_eq!(feed(&mut chord, chars("jk")), ["j", "k"]);
+        assert_eq!(chord.push(Key::Enter).as_deref(), Some("<CR>"));
+        assert_eq!(chord.push(Key::Ctrl('d')).as_deref(), Some("<C-d>"));
+        assert_eq!(chord.as_str(), "");
+    }
+
+    #[test]
+    fn counts_wait_for_their_action() {
+        let mut chord = Chord::default();
+
+        assert_eq!(feed(&mut chord, chars("12")), Vec::<String>::new());
+        assert_eq!(chord.as_str(), "12");
+        assert_eq!(chord.push(Key::Char('j')).as_deref(), Some("12j"));
+
+        assert_eq!(feed(&mut chord, chars("3")), Vec::<String>::new());
+        assert_eq!(chord.push(Key::Enter).as_deref(), Some("3<CR>"));
+        assert_eq!(chord.as_str(), "");
+    }
+
+    #[test]
+    fn chord_prefixes_wait_for_the_next_key() {
+        let mut chord = Chord::default();
+
+        assert_eq!(chord.push(Key::Char('g')), None);
+        assert_eq!(chord.as_str(), "g");
+        assert_eq!(chord.push(Key::Char('g')).as_deref(), Some("gg"));
+
+        assert_eq!(feed(&mut chord, chars("3dd")), ["3dd"]);
+        // Not a chord after all, handed over whole so it can be ignored
+        assert_eq!(feed(&mut chord, chars("dj")), ["dj"]);
+    }
+
+    #[test]
+    fn esc_and_backspace_edit_the_pending_chord() {
+        let mut chord = Chord::default();
+
+        feed(&mut chord, chars("12d"));
+        assert_eq!(chord.push(Key::Backspace), None);
+        assert_eq!(chord.as_str(), "12");
+        assert_eq!(chord.push(Key::Esc), None);
+        assert_eq!(chord.as_str(), "");
+
+        // With nothing pending they are keys of their own
+        assert_eq!(chord.push(Key::Esc).as_deref(), Some("<Esc>"));
+        assert_eq!(chord.push(Key::Backspace).as_deref(), Some("<BS>"));
+    }
+
+    #[test]
+    fn ignores_unknown_keys() {
+        let mut chord = Chord::default();
+
+        feed(&mut chord, chars("2g"));
+        assert_eq!(chord.push(Key::Unknown), None);
+        assert_eq!(chord.push(Key::Char('g')).as_deref(), Some("2gg"));
+    }
+}
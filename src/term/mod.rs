@@ -4,13 +4,13 @@ use libc::{c_ushort, ioctl, STDOUT_FILENO, TIOCGWINSZ};
 
 pub(crate) mod attribute;
 pub(crate) mod color;
+pub(crate) mod key;
+pub(crate) mod raw;
+pub(crate) mod resize;
+pub(crate) mod screen;
 
 pub(crate) const CSI: &str = "\x1B[";
 
-pub(crate) fn clear_screen() {
-    print!("{CSI}2J{CSI}1;1H");
-}
-
 #[derive(Debug)]
 pub(crate) struct Size {
     pub rows: u16,
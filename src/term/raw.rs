@@ -0,0 +1,85 @@
+use std::{
+    io::{self, IsTerminal},
+    mem,
+    sync::Mutex,
+};
+
+use libc::{
+    tcgetattr, tcsetattr, termios, BRKINT, ECHO, ICANON, ICRNL, IEXTEN, INPCK, ISIG, ISTRIP, IXON,
+    STDIN_FILENO, TCSAFLUSH, VMIN, VTIME,
+};
+
+// Settings from before raw mode was enabled, `None` while the terminal is untouched
+static ORIGINAL: Mutex<Option<termios>> = Mutex::new(None);
+
+/// Restores the terminal when dropped, hold it for as long as the TUI runs
+pub(crate) struct RawMode;
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        disable();
+    }
+}
+
+/// Switches stdin to unbuffered, unechoed input. Returns `None` when stdin is not a terminal,
+/// in which case input stays line-buffered.
+pub(crate) fn enable() -> Option<RawMode> {
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+
+    let mut original = ORIGINAL.lock().unwrap_or_else(|er| er.into_inner());
+    if original.is_some() {
+        return Some(RawMode);
+    }
+
+    let mut attrs: termios = unsafe { mem::zeroed() };
+    if unsafe { tcgetattr(STDIN_FILENO, &mut attrs) } != 0 {
+        return None;
+    }
+
+    let mut raw = attrs;
+    // Output processing stays on so `println!` keeps translating "\n" to "\r\n"
+    raw.c_iflag &= !(BRKINT | ICRNL | INPCK | ISTRIP | IXON);
+    raw.c_lflag &= !(ECHO | ICANON | IEXTEN | ISIG);
+    raw.c_cc[VMIN] = 1;
+    raw.c_cc[VTIME] = 0;
+
+    if unsafe { tcsetattr(STDIN_FILENO, TCSAFLUSH, &raw) } != 0 {
+        return None;
+    }
+
+    *original = Some(attrs);
+    drop(original);
+
+    install_panic_hook();
+
+    Some(RawMode)
+}
+
+/// Puts the terminal back the way it was found, safe to call more than once
+pub(crate) fn disable() {
+    let mut original = ORIGINAL.lock().unwrap_or_else(|er| er.into_inner());
+
+    if let Some(attrs) = original.take() {
+        unsafe { tcsetattr(STDIN_FILENO, TCSAFLUSH, &attrs) };
+    }
+}
+
+pub(crate) fn is_enabled() -> bool {
+    ORIGINAL
+        .lock()
+        .unwrap_or_else(|er| er.into_inner())
+        .is_some()
+}
+
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        // Without this the panic message is printed in raw mode and the shell is left unusable
+        disable();
+        super::screen::leave();
+        default_hook(info);
+    }));
+}
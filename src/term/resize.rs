@@ -0,0 +1,34 @@
+use std::{
+    mem, ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use libc::{c_int, sigaction, sigemptyset, sighandler_t, SIGWINCH};
+
+// Set from the signal handler, so it can't be anything fancier than an atomic
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_resize(_: c_int) {
+    PENDING.store(true, Ordering::Relaxed);
+}
+
+/// Installs the SIGWINCH handler. It leaves out `SA_RESTART` on purpose, so a read blocked
+/// on stdin gets interrupted and the screen can be redrawn without waiting for a key.
+pub(crate) fn listen() {
+    unsafe {
+        let mut action: sigaction = mem::zeroed();
+        action.sa_sigaction = on_resize as extern "C" fn(c_int) as sighandler_t;
+        sigemptyset(&mut action.sa_mask);
+
+        sigaction(SIGWINCH, &action, ptr::null_mut());
+    }
+}
+
+pub(crate) fn is_pending() -> bool {
+    PENDING.load(Ordering::Relaxed)
+}
+
+/// Whether the terminal was resized since the last call
+pub(crate) fn take() -> bool {
+    PENDING.swap(false, Ordering::Relaxed)
+}
@@ -0,0 +1,450 @@
+use std::{
+    fmt::{self, Write as _},
+    io::{self, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use unicode_width::UnicodeWidthChar;
+
+use super::CSI;
+
+// Text of the frame being drawn, `None` outside of `begin`/`present` so `draw!` prints straight out
+static FRAME: Mutex<Option<String>> = Mutex::new(None);
+
+// What the terminal shows, `None` when unknown and the next frame has to be drawn in full
+static SHOWN: Mutex<Option<Buffer>> = Mutex::new(None);
+
+static IS_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Like `print!`, but into the frame being drawn
+macro_rules! draw {
+    ($($arg:tt)*) => {
+        $crate::term::screen::write(format_args!($($arg)*))
+    };
+}
+
+/// Like `println!`, but into the frame being drawn
+macro_rules! drawln {
+    () => {
+        $crate::term::screen::write(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {{
+        $crate::term::screen::write(format_args!($($arg)*));
+        $crate::term::screen::write(format_args!("\n"));
+    }};
+}
+
+pub(crate) use {draw, drawln};
+
+/// Leaves the alternate screen when dropped, hold it for as long as the TUI runs
+pub(crate) struct AltScreen;
+
+impl Drop for AltScreen {
+    fn drop(&mut self) {
+        leave();
+    }
+}
+
+/// Switches to the alternate screen with the cursor hidden, so the shell's scrollback is left
+/// alone. Returns `None` when stdout is not a terminal.
+pub(crate) fn enter() -> Option<AltScreen> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+
+    if !IS_ACTIVE.swap(true, Ordering::Relaxed) {
+        print!("{CSI}?1049h{CSI}?25l");
+        io::stdout().flush().unwrap();
+        invalidate();
+    }
+
+    Some(AltScreen)
+}
+
+/// Hides the cursor again when dropped
+pub(crate) struct VisibleCursor;
+
+impl Drop for VisibleCursor {
+    fn drop(&mut self) {
+        if IS_ACTIVE.load(Ordering::Relaxed) {
+            print!("{CSI}?25l");
+            let _ = io::stdout().flush();
+        }
+    }
+}
+
+/// Shows the cursor while text is being typed, `None` outside of the alternate screen where it
+/// was never hidden
+pub(crate) fn show_cursor() -> Option<VisibleCursor> {
+    if !IS_ACTIVE.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    print!("{CSI}?25h");
+    io::stdout().flush().unwrap();
+
+    Some(VisibleCursor)
+}
+
+/// Brings back the original screen and cursor, safe to call more than once
+pub(crate) fn leave() {
+    if IS_ACTIVE.swap(false, Ordering::Relaxed) {
+        print!("{CSI}0m{CSI}?25h{CSI}?1049l");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Starts collecting `draw!` output for a new frame
+pub(crate) fn begin() {
+    *lock(&FRAME) = Some(String::new());
+}
+
+pub(crate) fn write(args: fmt::Arguments) {
+    match lock(&FRAME).as_mut() {
+        // Writing into a `String` can't fail
+        Some(frame) => frame.write_fmt(args).unwrap(),
+        None => print!("{args}"),
+    }
+}
+
+/// Writes the cells that changed since the last frame, then leaves the cursor on the line
+/// after it for the prompt
+pub(crate) fn present(rows: usize, cols: usize) {
+    let Some(text) = lock(&FRAME).take() else {
+        return;
+    };
+
+    let frame = Buffer::parse(&text, rows, cols);
+    let mut shown = lock(&SHOWN);
+
+    let mut out = String::new();
+    frame.draw_over(shown.as_ref(), &mut out);
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(out.as_bytes()).unwrap();
+    stdout.flush().unwrap();
+
+    *shown = Some(frame);
+}
+
+/// Forgets what is on screen, for when something else drew over it or the terminal was resized
+pub(crate) fn invalidate() {
+    *lock(&SHOWN) = None;
+}
+
+/// Rows `line` takes up once it wraps the way `Buffer::parse` lays it out, at least one
+pub(crate) fn height(line: &str, cols: usize) -> usize {
+    let mut rows = 1;
+    let mut col = 0;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1B' {
+            // Up to the end of the escape sequence, it takes no room
+            chars
+                .by_ref()
+                .skip(1)
+                .find(|c| ('\x40'..='\x7E').contains(c));
+            continue;
+        }
+
+        let width = c.width().unwrap_or(0);
+        if width == 0 || width > cols {
+            continue;
+        }
+
+        if col + width > cols {
+            rows += 1;
+            col = 0;
+        }
+        col += width;
+    }
+
+    rows
+}
+
+/// How many of `lines`, from the first, fit in `rows` rows once they wrap
+pub(crate) fn fitting(lines: &[String], rows: usize, cols: usize) -> usize {
+    let mut used = 0;
+
+    lines
+        .iter()
+        .take_while(|line| {
+            used += height(line, cols);
+            used <= rows
+        })
+        .count()
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|er| er.into_inner())
+}
+
+#[derive(Clone, PartialEq)]
+struct Cell {
+    /// `None` for the second half of a wide character, which is drawn along with its first half
+    symbol: Option<char>,
+    /// SGR sequences in effect, empty for the default look
+    style: String,
+}
+
+impl Cell {
+    const BLANK: Cell = Cell {
+        symbol: Some(' '),
+        style: String::new(),
+    };
+}
+
+struct Buffer {
+    rows: usize,
+    cols: usize,
+    cells: Vec<Cell>,
+    /// Where the text ended, the prompt is printed there after the frame
+    end_row: usize,
+}
+
+impl Buffer {
+    fn blank(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            cells: vec![Cell::BLANK; rows * cols],
+            end_row: 0,
+        }
+    }
+
+    /// Lays out text the way the terminal would, wrapping long lines. Only colors and attributes
+    /// are understood out of the escape sequences. The last row is kept for the prompt.
+    fn parse(text: &str, rows: usize, cols: usize) -> Self {
+        let mut buffer = Self::blank(rows, cols);
+        let (mut row, mut col) = (0, 0);
+        let mut style = String::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1B' => {
+                    let Some('[') = chars.next() else {
+                        continue;
+                    };
+
+                    let mut params = String::new();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7E').contains(&c) {
+                            if c == 'm' {
+                                match params.as_str() {
+                                    "" | "0" => style.clear(),
+                                    _ => style.push_str(&format!("{CSI}{params}m")),
+                                }
+                            }
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                '\n' => {
+                    row += 1;
+                    col = 0;
+                }
+                '\r' => col = 0,
+                _ => {
+                    let c = if c == '\t' { ' ' } else { c };
+                    let width = c.width().unwrap_or(0);
+                    if width == 0 || width > cols {
+                        continue;
+                    }
+
+                    if col + width > cols {
+                        row += 1;
+                        col = 0;
+                    }
+
+                    buffer.set(row, col, Some(c), &style);
+                    if width == 2 {
+                        buffer.set(row, col + 1, None, &style);
+                    }
+                    col += width;
+                }
+            }
+        }
+
+        let end_row = if col > 0 { row + 1 } else { row };
+        buffer.end_row = end_row.min(rows.saturating_sub(1));
+
+        buffer
+    }
+
+    fn set(&mut self, row: usize, col: usize, symbol: Option<char>, style: &str) {
+        if row + 1 >= self.rows {
+            return;
+        }
+
+        self.cells[row * self.cols + col] = Cell {
+            symbol,
+            style: style.to_string(),
+        };
+    }
+
+    /// Appends what it takes to turn `shown` into this buffer on the terminal
+    fn draw_over(&self, shown: Option<&Buffer>, out: &mut String) {
+        out.push_str(&format!("{CSI}0m"));
+
+        let shown = shown.filter(|shown| shown.rows == self.rows && shown.cols == self.cols);
+        if shown.is_none() {
+            out.push_str(&format!("{CSI}2J"));
+        }
+
+        let mut cursor = None;
+        let mut style = "";
+
+        for row in 0..self.rows {
+            // The prompt was printed over this row behind our back
+            let is_dirty = shown.is_some_and(|shown| shown.end_row == row);
+            if is_dirty {
+                out.push_str(&format!("{CSI}{};1H{CSI}2K", row + 1));
+                cursor = Some((row, 0));
+            }
+
+            for col in 0..self.cols {
+                let idx = row * self.cols + col;
+                let cell = &self.cells[idx];
+                let before = match shown {
+                    Some(shown) if !is_dirty => &shown.cells[idx],
+                    _ => &Cell::BLANK,
+                };
+
+                if cell == before {
+                    continue;
+                }
+
+                let Some(symbol) = cell.symbol else {
+                    continue;
+                };
+
+                if cursor != Some((row, col)) {
+                    out.push_str(&format!("{CSI}{};{}H", row + 1, col + 1));
+                }
+
+                if cell.style != style {
+                    out.push_str(&format!("{CSI}0m{}", cell.style));
+                    style = &cell.style;
+                }
+
+                out.push(symbol);
+                cursor = Some((row, col + symbol.width().unwrap_or(1)));
+            }
+        }
+
+        out.push_str(&format!("{CSI}0m{CSI}{};1H", self.end_row + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(buffer: &Buffer, row: usize) -> String {
+        let cells = &buffer.cells[row * buffer.cols..(row + 1) * buffer.cols];
+        cells.iter().filter_map(|cell| cell.symbol).collect()
+    }
+
+    #[test]
+    fn parses_lines_and_wraps_long_ones() {
+        let buffer = Buffer::parse("ab\ncdefgh\n", 5, 4);
+
+        assert_eq!(text(&buffer, 0), "ab  ");
+        assert_eq!(text(&buffer, 1), "cdef");
+        assert_eq!(text(&buffer, 2), "gh  ");
+        assert_eq!(buffer.end_row, 3);
+    }
+
+    #[test]
+    fn parses_wide_characters_as_two_cells() {
+        let buffer = Buffer::parse("a葬送", 3, 4);
+
+        assert_eq!(buffer.cells[1].symbol, Some('葬'));
+        assert_eq!(buffer.cells[2].symbol, None);
+        // Doesn't fit in the last column, so it goes to the next row
+        assert!(buffer.cells[3] == Cell::BLANK);
+        assert_eq!(text(&buffer, 1), "送  ");
+    }
+
+    #[test]
+    fn parses_colors_and_skips_other_escapes() {
+        let buffer = Buffer::parse("\x1B[31ma\x1B[1mb\x1B[0mc\x1B[2Kd", 2, 5);
+
+        assert_eq!(text(&buffer, 0), "abcd ");
+        assert_eq!(buffer.cells[0].style, "\x1B[31m");
+        assert_eq!(buffer.cells[1].style, "\x1B[31m\x1B[1m");
+        assert_eq!(buffer.cells[2].style, "");
+        assert_eq!(buffer.cells[3].style, "");
+    }
+
+    #[test]
+    fn keeps_the_last_row_for_the_prompt() {
+        let buffer = Buffer::parse("a\nb\nc\nd\n", 3, 2);
+
+        assert_eq!(text(&buffer, 1), "b ");
+        assert_eq!(text(&buffer, 2), "  ");
+        assert_eq!(buffer.end_row, 2);
+    }
+
+    #[test]
+    fn draws_everything_over_nothing() {
+        let buffer = Buffer::parse("ab\n", 3, 4);
+        let mut out = String::new();
+        buffer.draw_over(None, &mut out);
+
+        assert_eq!(out, "\x1B[0m\x1B[2J\x1B[1;1Hab\x1B[0m\x1B[2;1H");
+    }
+
+    #[test]
+    fn draws_only_what_changed() {
+        let shown = Buffer::parse("abc\nxyz\n", 4, 4);
+        let frame = Buffer::parse("abd\nxyz\n", 4, 4);
+        let mut out = String::new();
+        frame.draw_over(Some(&shown), &mut out);
+
+        // The prompt row is cleared too, since it was typed over
+        assert_eq!(out, "\x1B[0m\x1B[1;3Hd\x1B[3;1H\x1B[2K\x1B[0m\x1B[3;1H");
+    }
+
+    #[test]
+    fn draws_everything_after_a_resize() {
+        let shown = Buffer::parse("ab\n", 3, 4);
+        let frame = Buffer::parse("ab\n", 3, 5);
+        let mut out = String::new();
+        frame.draw_over(Some(&shown), &mut out);
+
+        assert!(out.contains("\x1B[2J"));
+        assert!(out.contains("ab"));
+    }
+
+    #[test]
+    fn draws_styles_only_when_they_change() {
+        let frame = Buffer::parse("\x1B[32mab\x1B[0mc\n", 2, 4);
+        let mut out = String::new();
+        frame.draw_over(None, &mut out);
+
+        assert_eq!(
+            out,
+            "\x1B[0m\x1B[2J\x1B[1;1H\x1B[0m\x1B[32mab\x1B[0mc\x1B[0m\x1B[2;1H"
+        );
+    }
+
+    #[test]
+    fn measures_wrapped_lines() {
+        assert_eq!(height("", 4), 1);
+        assert_eq!(height("abcd", 4), 1);
+        assert_eq!(height("abcde", 4), 2);
+        assert_eq!(height("\x1B[31mabcd\x1B[0m", 4), 1);
+        assert_eq!(height("a葬送", 4), 2);
+
+        let lines = ["ab", "abcdef", "c"].map(str::to_string);
+        assert_eq!(fitting(&lines, 3, 4), 2);
+        assert_eq!(fitting(&lines, 2, 4), 1);
+    }
+}
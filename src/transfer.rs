@@ -0,0 +1,444 @@
+use std::{
+    fmt::Display,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use rusqlite::Connection;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::series::{Query, Series};
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Self::parse)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum TransferError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    Xml(quick_xml::Error),
+    Db(rusqlite::Error),
+}
+
+impl Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::Io(er) => write!(f, "{er}"),
+            TransferError::Json(er) => write!(f, "invalid JSON: {er}"),
+            TransferError::Csv(er) => write!(f, "invalid CSV: {er}"),
+            TransferError::Xml(er) => write!(f, "invalid XML: {er}"),
+            TransferError::Db(er) => write!(f, "{er}"),
+        }
+    }
+}
+
+impl From<io::Error> for TransferError {
+    fn from(er: io::Error) -> Self {
+        TransferError::Io(er)
+    }
+}
+
+impl From<serde_json::Error> for TransferError {
+    fn from(er: serde_json::Error) -> Self {
+        TransferError::Json(er)
+    }
+}
+
+impl From<csv::Error> for TransferError {
+    fn from(er: csv::Error) -> Self {
+        TransferError::Csv(er)
+    }
+}
+
+impl From<quick_xml::Error> for TransferError {
+    fn from(er: quick_xml::Error) -> Self {
+        TransferError::Xml(er)
+    }
+}
+
+impl From<rusqlite::Error> for TransferError {
+    fn from(er: rusqlite::Error) -> Self {
+        TransferError::Db(er)
+    }
+}
+
+/// Writes the whole library, returns how many series went out
+pub(crate) fn export(
+    conn: &mut Connection,
+    format: Format,
+    writer: impl Write,
+) -> Result<usize, TransferError> {
+    // Read from one snapshot so a concurrent write can't tear the export
+    let tx = conn.transaction()?;
+
+    let query = Query::default();
+    let total = Series::count_in(&tx, &query)?;
+    let series = Series::get_in(&tx, total, 0, &query)?;
+
+    match format {
+        Format::Json => {
+            let mut writer = writer;
+            serde_json::to_writer_pretty(&mut writer, &series)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for series in &series {
+                writer.serialize(series)?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(series.len())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Action {
+    Insert,
+    /// Matched an existing series on its guid
+    UpdateByGuid,
+    /// No guid match, but an existing series had the same name
+    UpdateByName,
+    /// Failed `Series::check`, left out of the import
+    Skip,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Change {
+    pub(crate) action: Action,
+    pub(crate) guid: String,
+    pub(crate) name: String,
+    /// Why the row was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) problem: Option<String>,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.action {
+            Action::Insert => "insert",
+            Action::UpdateByGuid => "update (same guid)",
+            Action::UpdateByName => "update (same name)",
+            Action::Skip => "skip",
+        };
+
+        write!(f, "{action:<20} {}  {}", self.guid, self.name)?;
+        if let Some(problem) = &self.problem {
+            write!(f, " ({problem})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads series from `reader` and writes them in a single transaction. Without `merge`
+/// every row is inserted and a clashing guid aborts the import. Rows without a guid get a new
+/// one. Rows that fail
+/// `Series::check` are skipped and reported. With `dry_run` the changes are worked out and
+/// then rolled back.
+pub(crate) fn import(
+    conn: &mut Connection,
+    format: Format,
+    reader: impl Read,
+    merge: bool,
+    dry_run: bool,
+) -> Result<Vec<Change>, TransferError> {
+    let incoming: Vec<Series> = match format {
+        Format::Json => serde_json::from_reader(reader)?,
+        Format::Csv => csv::Reader::from_reader(reader)
+            .deserialize()
+            .collect::<Result<_, _>>()?,
+    };
+
+    let tx = conn.transaction()?;
+    let mut changes = vec![];
+
+    for mut series in incoming {
+        // Hand-written files may not have one, and an empty primary key would take the first
+        if series.guid.trim().is_empty() {
+            series.guid = Uuid::new_v4().to_string();
+        }
+
+        if let Err(problem) = series.check() {
+            changes.push(Change {
+                action: Action::Skip,
+                guid: series.guid,
+                name: series.name,
+                problem: Some(problem),
+            });
+            continue;
+        }
+
+        let existing = match merge {
+            true => match Series::get_by_guid(&tx, &series.guid)? {
+                Some(existing) => Some((existing, Action::UpdateByGuid)),
+                None => Series::get_by_name(&tx, &series.name)?
+                    .map(|existing| (existing, Action::UpdateByName)),
+            },
+            false => None,
+        };
+
+        let action = match existing {
+            Some((existing, action)) => {
+                series.guid = existing.guid;
+                series.try_update_quietly(&tx)?;
+
+                action
+            }
+            None => {
+                series.insert(&tx)?;
+
+                Action::Insert
+            }
+        };
+
+        changes.push(Change {
+            action,
+            guid: series.guid,
+            name: series.name,
+            problem: None,
+        });
+    }
+
+    match dry_run {
+        true => tx.rollback()?,
+        false => tx.commit()?,
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, season::Season, series::WatchStatus};
+
+    fn library() -> Connection {
+        let conn = db::open_in_memory();
+
+        let mut frieren = Series::new(
+            "Sousou no Frieren".to_string(),
+            None,
+            Some(true),
+            Some(28),
+            Some(10),
+            None,
+        );
+        frieren.rating = Some(9);
+        frieren.notes = "Watch with subs,\n\"not\" dubs".to_string();
+        frieren.tags = vec!["fantasy".to_string(), "slow".to_string()];
+        frieren.insert(&conn).unwrap();
+
+        let mut bocchi = Series::new("Bocchi the Rock!".to_string(), None, None, None, None, None);
+        bocchi.seasons = vec![
+            Season {
+                total_episodes: 12,
+                current_episode: 12,
+            },
+            Season {
+                total_episodes: 0,
+                current_episode: 3,
+            },
+        ];
+        bocchi.insert(&conn).unwrap();
+
+        conn
+    }
+
+    /// Comparable regardless of the order the library lists them in
+    fn snapshot(conn: &Connection) -> Vec<serde_json::Value> {
+        let mut series = Series::get_in(conn, 100, 0, &Query::default()).unwrap();
+        series.sort_by(|a, b| a.guid.cmp(&b.guid));
+
+        series
+            .iter()
+            .map(|series| serde_json::to_value(series).unwrap())
+            .collect()
+    }
+
+    fn exported(conn: &mut Connection, format: Format) -> Vec<u8> {
+        let mut out = vec![];
+        export(conn, format, &mut out).unwrap();
+
+        out
+    }
+
+    #[test]
+    fn round_trips_json_and_csv() {
+        for format in [Format::Json, Format::Csv] {
+            let mut source = library();
+            let out = exported(&mut source, format);
+
+            let mut target = db::open_in_memory();
+            let changes = import(&mut target, format, out.as_slice(), false, false).unwrap();
+
+            assert_eq!(changes.len(), 2);
+            assert!(changes
+                .iter()
+                .all(|change| matches!(change.action, Action::Insert)));
+            assert_eq!(snapshot(&target), snapshot(&source));
+        }
+    }
+
+    #[test]
+    fn clashing_guids_need_merge() {
+        let mut conn = library();
+        let out = exported(&mut conn, Format::Json);
+
+        assert!(matches!(
+            import(&mut conn, Format::Json, out.as_slice(), false, false),
+            Err(TransferError::Db(_))
+        ));
+        // Nothing got in before the clash either
+        assert_eq!(snapshot(&conn).len(), 2);
+    }
+
+    #[test]
+    fn merges_by_guid_then_by_name() {
+        let mut conn = library();
+        let before = Series::get_in(&conn, 100, 0, &Query::default()).unwrap();
+
+        let mut by_guid = before[0].clone();
+        by_guid.current_episode = by_guid.current_episode.min(1);
+        by_guid.seasons.clear();
+        by_guid.status = WatchStatus::Dropped;
+
+        let mut by_name = before[1].clone();
+        by_name.guid = "not-in-the-library".to_string();
+        by_name.notes = "Renamed guid".to_string();
+
+        let new = Series::new("Dandadan".to_string(), None, None, Some(12), None, None);
+
+        let json = serde_json::to_vec(&[&by_guid, &by_name, &new]).unwrap();
+        let changes = import(&mut conn, Format::Json, json.as_slice(), true, false).unwrap();
+
+        assert!(matches!(changes[0].action, Action::UpdateByGuid));
+        assert!(matches!(changes[1].action, Action::UpdateByName));
+        // Takes over the guid of the one it updates
+        assert_eq!(changes[1].guid, before[1].guid);
+        assert!(matches!(changes[2].action, Action::Insert));
+
+        let after = |guid: &str| Series::get_by_guid(&conn, guid).unwrap().unwrap();
+        assert_eq!(after(&by_guid.guid).status, WatchStatus::Dropped);
+        assert_eq!(after(&before[1].guid).notes, "Renamed guid");
+        assert_eq!(snapshot(&conn).len(), 3);
+    }
+
+    #[test]
+    fn dry_run_leaves_the_library_alone() {
+        let mut conn = library();
+        let before = snapshot(&conn);
+
+        let mut changed = Series::get_in(&conn, 100, 0, &Query::default()).unwrap();
+        changed[0].notes = "Changed".to_string();
+        changed.push(Series::new(
+            "Dandadan".to_string(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ));
+
+        let json = serde_json::to_vec(&changed).unwrap();
+        let changes = import(&mut conn, Format::Json, json.as_slice(), true, true).unwrap();
+
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(changes[2].action, Action::Insert));
+        assert_eq!(snapshot(&conn), before);
+    }
+
+    #[test]
+    fn gives_rows_without_a_guid_a_new_one() {
+        let mut conn = db::open_in_memory();
+        let json = r#"[
+            {"guid": "", "name": "Blank", "is_finished": false, "is_airing_finished": false,
+             "total_episodes": 12, "current_episode": 0, "status": "plan_to_watch"},
+            {"name": "Missing", "is_finished": false, "is_airing_finished": false,
+             "total_episodes": 12, "current_episode": 0, "status": "plan_to_watch"}
+        ]"#;
+
+        let changes = import(&mut conn, Format::Json, json.as_bytes(), false, false).unwrap();
+
+        assert!(changes
+            .iter()
+            .all(|change| matches!(change.action, Action::Insert)));
+        assert!(changes.iter().all(|change| change.guid.len() == 36));
+        assert_ne!(changes[0].guid, changes[1].guid);
+        assert_eq!(snapshot(&conn).len(), 2);
+    }
+
+    #[test]
+    fn keeps_the_timestamps() {
+        let mut conn = db::open_in_memory();
+
+        let mut old = Series::new("Old".to_string(), None, None, None, None, None);
+        old.added_at = Some("2020-01-01 00:00:00".to_string());
+        old.updated_at = Some("2021-06-30 12:00:00".to_string());
+        let mut garbled = Series::new("Garbled".to_string(), None, None, None, None, None);
+        garbled.added_at = Some("yesterday".to_string());
+        let new = Series::new("New".to_string(), None, None, None, None, None);
+
+        let json = serde_json::to_vec(&[&old, &garbled, &new]).unwrap();
+        let changes = import(&mut conn, Format::Json, json.as_slice(), false, false).unwrap();
+        assert!(matches!(changes[1].action, Action::Skip));
+
+        let stored = |guid: &str| Series::get_by_guid(&conn, guid).unwrap().unwrap();
+        assert_eq!(stored(&old.guid).added_at, old.added_at);
+        assert_eq!(stored(&old.guid).updated_at, old.updated_at);
+        // Stamped now when the file has none
+        assert!(stored(&new.guid).added_at.is_some());
+        assert_eq!(stored(&new.guid).updated_at, None);
+    }
+
+    #[test]
+    fn skips_invalid_rows() {
+        let mut conn = db::open_in_memory();
+
+        let valid = Series::new("Valid".to_string(), None, None, Some(12), None, None);
+        let mut rated = valid.clone();
+        rated.guid = "rated".to_string();
+        rated.rating = Some(11);
+        let mut negative = valid.clone();
+        negative.guid = "negative".to_string();
+        negative.current_episode = -1;
+        let mut past = valid.clone();
+        past.guid = "past".to_string();
+        past.current_episode = 13;
+
+        let json = serde_json::to_vec(&[&valid, &rated, &negative, &past]).unwrap();
+        let changes = import(&mut conn, Format::Json, json.as_slice(), false, false).unwrap();
+
+        assert!(matches!(changes[0].action, Action::Insert));
+        for change in &changes[1..] {
+            assert!(matches!(change.action, Action::Skip));
+            assert!(change.problem.is_some());
+        }
+        assert_eq!(snapshot(&conn).len(), 1);
+    }
+}
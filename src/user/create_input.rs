@@ -1,9 +1,16 @@
 use super::InputType;
+use crate::{
+    season::{self, Season},
+    series::{parse_tags, WatchStatus, RATING_RANGE},
+};
 
 pub(crate) struct CreateInput {
     pub(crate) label: String,
     pub(crate) input_type: InputType,
     pub(crate) raw_value: String,
+    pub(crate) required: bool,
+    /// Why the value was turned down by the last `validate`
+    pub(crate) error: Option<String>,
 }
 
 impl CreateInput {
@@ -14,30 +21,102 @@ impl CreateInput {
             InputType::Number => "0",
             InputType::String => "",
             InputType::Boolean => "false",
+            InputType::Status
+            | InputType::Rating
+            | InputType::Text
+            | InputType::Tags
+            | InputType::Seasons => "",
         };
 
         Self {
             label,
             input_type,
             raw_value: value.to_string(),
+            required: false,
+            error: None,
         }
     }
 
-    pub(crate) fn get_i32(&self) -> Option<i32> {
-        if self.input_type != InputType::Number {
-           return None; 
+    /// Turns down blank values
+    pub(crate) fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub(crate) fn set_value<T: ToString>(&mut self, value: T) {
+        self.raw_value = value.to_string();
+    }
+
+    /// Checks the value against its type and remembers the error, `true` when it is fine
+    pub(crate) fn validate(&mut self) -> bool {
+        self.error = match self.input_type {
+            _ if self.required && self.raw_value.trim().is_empty() => {
+                Some("can't be empty".to_string())
+            }
+            InputType::Number => self.get_i32().err(),
+            InputType::Boolean => self.get_bool().err(),
+            InputType::Status => self.get_status().err(),
+            InputType::Rating => self.get_rating().err(),
+            InputType::Seasons => self.get_seasons().err(),
+            InputType::String | InputType::Text | InputType::Tags => None,
+        };
+
+        self.error.is_none()
+    }
+
+    pub(crate) fn get_i32(&self) -> Result<i32, String> {
+        self.raw_value
+            .trim()
+            .parse::<i32>()
+            .ok()
+            .filter(|nr| *nr >= 0)
+            .ok_or_else(|| "must be a whole number, 0 or more".to_string())
+    }
+
+    /// Prefilled values come from `bool::to_string`, so "true" and "false" have to round-trip
+    pub(crate) fn get_bool(&self) -> Result<bool, String> {
+        match self.raw_value.trim().to_ascii_lowercase().as_str() {
+            "yes" | "y" | "true" => Ok(true),
+            "no" | "n" | "false" => Ok(false),
+            _ => Err("must be yes or no".to_string()),
+        }
+    }
+
+    /// `Ok(None)` when left empty, `Err` when it is not a whole number from 1 to 10
+    pub(crate) fn get_rating(&self) -> Result<Option<u8>, String> {
+        let value = self.raw_value.trim();
+        if value.is_empty() {
+            return Ok(None);
         }
 
-        match self.raw_value.parse::<i32>() {
-            Ok(nr) => Some(nr),
-            Err(_) => None,
+        match value.parse::<u8>() {
+            Ok(rating) if RATING_RANGE.contains(&rating) => Ok(Some(rating)),
+            _ => Err(format!(
+                "must be a number from {} to {}",
+                RATING_RANGE.start(),
+                RATING_RANGE.end()
+            )),
         }
     }
 
-    pub(crate) fn get_bool(&self) -> Option<bool> {
-        match self.input_type {
-            InputType::Boolean => Some(self.raw_value.to_ascii_lowercase() == "yes"),
-            _ => None,
+    /// `Ok(None)` when left empty, so the status can be worked out from the progress instead
+    pub(crate) fn get_status(&self) -> Result<Option<WatchStatus>, String> {
+        if self.raw_value.trim().is_empty() {
+            return Ok(None);
+        }
+
+        match WatchStatus::parse(&self.raw_value) {
+            Some(status) => Ok(Some(status)),
+            None => Err("unknown status".to_string()),
         }
     }
+
+    pub(crate) fn get_tags(&self) -> Vec<String> {
+        parse_tags(&self.raw_value)
+    }
+
+    /// Empty for a single-season series
+    pub(crate) fn get_seasons(&self) -> Result<Vec<Season>, String> {
+        season::parse(&self.raw_value)
+    }
 }
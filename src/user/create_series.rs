@@ -1,75 +1,258 @@
+use std::sync::Arc;
+
 use crate::{
     app::App,
+    db::get_connection,
     input,
     keybinds::{
-        nav::{move_down, move_max, move_min, move_to, move_up, start_listing},
-        parse_input,
+        nav::{open_form, start_listing},
+        parse_input, UserInput,
     },
+    season,
     series::Series,
-    term::attribute,
+    term::{
+        attribute,
+        color::Color,
+        screen::{draw, drawln},
+    },
+    user::{create_input::CreateInput, InputType},
     Mode,
 };
 
 pub(crate) fn create_render(app: &mut App) {
+    match &app.edited_series {
+        Some(series) => drawln!("Edit series: {}", series.name),
+        None => drawln!("New series"),
+    }
+    drawln!();
+
     for (i, input) in app.create_data.iter().enumerate() {
         let label = input.label.clone();
 
-        if i == app.data.hovered_series_idx {
-            print!("{}", attribute::invert());
+        if i == app.hovered_field_idx {
+            draw!("{}", attribute::invert());
         }
 
-        if app.mode == Mode::Edit && i != app.data.hovered_series_idx {
+        if app.mode == Mode::Edit && i != app.hovered_field_idx {
             continue;
         }
 
-        println!("{}{}: {:?}", label, attribute::reset(), input.raw_value);
+        let hint = match input.input_type {
+            InputType::Status => {
+                "  (watching, completed, dropped or plan to watch, empty to guess from progress)"
+            }
+            InputType::Rating => "  (1 to 10, empty for none)",
+            InputType::Tags => "  (comma separated)",
+            InputType::Seasons => {
+                "  (watched/total per season like 12/12, 5/24, replaces the episodes above)"
+            }
+            _ => "",
+        };
+
+        match &input.error {
+            Some(error) => drawln!(
+                "{}{}: {:?}  {}{}{}",
+                label,
+                attribute::reset(),
+                input.raw_value,
+                Color::Red,
+                error,
+                attribute::reset()
+            ),
+            None => drawln!(
+                "{}{}: {:?}{}",
+                label,
+                attribute::reset(),
+                input.raw_value,
+                hint
+            ),
+        }
     }
 }
 
 pub(crate) fn create_key_handler(app: &mut App) {
-    let user_input = input::get();
+    app.should_render = true;
 
     if app.mode == Mode::Edit {
-        app.create_data[app.data.hovered_series_idx].raw_value = user_input;
+        let field = &mut app.create_data[app.hovered_field_idx];
+        let value = match field.input_type {
+            InputType::Text => input::get_text(&field.raw_value),
+            _ => input::get_line(&field.raw_value),
+        };
+
+        if let Some(value) = value {
+            field.raw_value = value.trim().to_string();
+            field.error = None;
+        }
+
         app.mode = Mode::Navigation;
         return;
     }
 
+    let user_input = input::get();
+
     let input = parse_input(user_input.trim());
     let binding = input.actions.iter().collect::<String>();
     let binding = binding.as_str();
 
     match binding {
-        "q" => start_listing(app),
+        "q" | "<Esc>" => start_listing(app),
+
+        "k" | "<Up>" => move_field(app, -count(&input)),
+        "j" | "<Down>" => move_field(app, count(&input)),
+
+        "G" | "<End>" => move_field(app, isize::MAX),
+        "gg" | "<Home>" => move_field(app, isize::MIN),
+
+        "" | "<CR>" if input.digits_prefix.is_some() => {
+            app.hovered_field_idx = 0;
+            move_field(app, count(&input) - 1);
+        }
+
+        "e" | "<CR>" => app.mode = Mode::Edit,
+        "i" => save(app),
+        _ => {}
+    }
+}
+
+fn count(input: &UserInput) -> isize {
+    input.digits_prefix.unwrap_or(1) as isize
+}
+
+/// The form always fits on screen, so unlike the list there is nothing to scroll
+fn move_field(app: &mut App, offset: isize) {
+    let last = app.create_data.len().saturating_sub(1);
+    app.hovered_field_idx = app
+        .hovered_field_idx
+        .saturating_add_signed(offset)
+        .min(last);
+}
+
+fn save(app: &mut App) {
+    if !validate(&mut app.create_data) {
+        app.toast = Some("Some fields need fixing".to_string());
+        return;
+    }
+
+    // Everything below was just validated
+    let form = &app.create_data;
+    let seasons = form[9].get_seasons().unwrap_or_default();
+    let (current, total) = season::counts(&seasons, form[3].get_i32().ok(), form[4].get_i32().ok());
 
-        "k" => move_up(app, &input),
-        "j" => move_down(app, &input),
+    let mut series = Series::new(
+        form[0].raw_value.trim().to_string(),
+        form[1].get_bool().ok(),
+        form[2].get_bool().ok(),
+        total,
+        current,
+        form[5].get_status().ok().flatten(),
+    );
+    series.rating = form[6].get_rating().ok().flatten();
+    series.notes.clone_from(&form[7].raw_value);
+    series.tags = form[8].get_tags();
+    series.seasons = seasons;
 
-        "G" => move_max(app),
-        "gg" => move_min(app),
+    match &app.edited_series {
+        Some(edited) => {
+            series.guid.clone_from(&edited.guid);
 
-        "" => {
-            if input.digits_prefix.is_some() {
-                move_to(app, &input);
+            match get_connection().and_then(|conn| series.try_update(&conn)) {
+                Ok(_) => {
+                    app.toast = Some("Series updated!".to_string());
+                    app.data.ignore_cached_series = true;
+                    start_listing(app);
+                }
+                Err(er) => app.toast = Some(er.to_string()),
             }
         }
+        None => match get_connection().and_then(|conn| series.try_insert(&conn)) {
+            Ok(duplicates) if duplicates.is_empty() => created(app),
+            Ok(duplicates) => confirm_duplicate(app, series, duplicates),
+            Err(er) => app.toast = Some(er.to_string()),
+        },
+    }
+}
+
+fn created(app: &mut App) {
+    app.toast = Some("Series created!".to_string());
+    app.data.total_series += 1;
+    app.data.ignore_cached_series = true;
+}
+
+fn confirm_duplicate(app: &mut App, series: Series, duplicates: Vec<Series>) {
+    let mut toast = format!(
+        "\"{}\" looks like it is already in the library:",
+        series.name
+    );
+    for (i, duplicate) in duplicates.iter().enumerate() {
+        toast.push_str(&format!(
+            "\n  {}) {} ({}/{}, {})",
+            i + 1,
+            duplicate.name,
+            duplicate.current_episode,
+            duplicate.total_episodes,
+            duplicate.status.label()
+        ));
+    }
+    toast.push_str(
+        "\n[o] open the first one or [2o] another, [a] insert anyway, anything else goes back",
+    );
+
+    app.toast = Some(toast.clone());
+    app.keyboard_handler =
+        Arc::new(move |app| duplicate_key_handler(app, &series, &duplicates, &toast));
+}
+
+fn duplicate_key_handler(app: &mut App, series: &Series, duplicates: &[Series], prompt: &str) {
+    app.should_render = true;
+
+    let Some(user_input) = input::get_answer(app, prompt) else {
+        return;
+    };
 
-        "e" => app.mode = Mode::Edit,
-        "i" => {
-            let form = &app.create_data;
-            let mut series = Series::new(
-                form[0].raw_value.clone(),
-                form[1].get_bool(),
-                form[2].get_bool(),
-                form[3].get_i32(),
-                form[4].get_i32(),
-            );
-
-            match series.try_insert() {
-                Ok(_) => app.toast = Some("Series created!".to_string()),
+    app.keyboard_handler = Arc::new(create_key_handler);
+    let input = parse_input(user_input.trim());
+    let binding = input.actions.iter().collect::<String>();
+
+    match binding.as_str() {
+        "a" => {
+            let mut series = series.clone();
+            match get_connection().and_then(|conn| series.insert(&conn)) {
+                Ok(_) => created(app),
                 Err(er) => app.toast = Some(er.to_string()),
             }
         }
-        _ => {}
+        "o" => {
+            let idx = input.digits_prefix.unwrap_or(1).max(1) - 1;
+            let Some(existing) = duplicates.get(idx) else {
+                app.toast = Some(format!("There are only {} to pick from", duplicates.len()));
+                return;
+            };
+
+            open_form(app, Some(existing.clone()));
+        }
+        _ => app.toast = Some("Nothing inserted".to_string()),
+    }
+}
+
+/// Checks every field, then the ones that depend on each other
+fn validate(form: &mut [CreateInput]) -> bool {
+    let mut is_valid = true;
+    for field in form.iter_mut() {
+        is_valid &= field.validate();
+    }
+
+    if !is_valid {
+        return false;
     }
+
+    // Seasons replace both counts, so there is nothing to compare then
+    let current = form[3].get_i32().unwrap_or(0);
+    let total = form[4].get_i32().unwrap_or(0);
+    if form[9].get_seasons().unwrap_or_default().is_empty() && total > 0 && current > total {
+        form[3].error = Some(format!("can't be past the total of {total}"));
+        return false;
+    }
+
+    true
 }
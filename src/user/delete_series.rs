@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use crate::{
+    app::App, db::get_connection, history::WatchEvent, input, main_key_handler, series::Series,
+};
+
+pub(crate) fn start_deleting(app: &mut App) {
+    let Some(series) = app.data.available_series.get(app.data.hovered_series_idx) else {
+        return;
+    };
+
+    let series = series.clone();
+    app.toast = Some(confirm_prompt(&series));
+    app.keyboard_handler = Arc::new(move |app| confirm_key_handler(app, &series));
+}
+
+fn confirm_prompt(series: &Series) -> String {
+    format!("Delete \"{}\"? [y/N]", series.name)
+}
+
+fn confirm_key_handler(app: &mut App, series: &Series) {
+    app.should_render = true;
+
+    let Some(user_input) = input::get_answer(app, &confirm_prompt(series)) else {
+        return;
+    };
+
+    app.keyboard_handler = Arc::new(main_key_handler);
+    if !user_input.trim().eq_ignore_ascii_case("y") {
+        app.toast = Some("Nothing deleted".to_string());
+        return;
+    }
+
+    // Fresh from the database, so undo puts back the very row with its timestamps
+    let deleted = get_connection().and_then(|conn| {
+        let mut series = Series::get_by_guid(&conn, &series.guid)?.unwrap_or(series.clone());
+        series.try_delete(&conn)?;
+
+        Ok(series)
+    });
+
+    match deleted {
+        Ok(series) => {
+            app.toast = Some(format!("Deleted \"{}\", press [u] to undo", series.name));
+            forget_deleted(app);
+            app.data.last_deleted = Some(series);
+            app.data.total_series = app.data.total_series.saturating_sub(1);
+            app.data.ignore_cached_series = true;
+            app.data.clamp_cursor();
+        }
+        Err(er) => app.toast = Some(er.to_string()),
+    }
+}
+
+/// Only the last delete can be undone, the history of the one before it goes for good
+pub(crate) fn forget_deleted(app: &mut App) {
+    let Some(series) = app.data.last_deleted.take() else {
+        return;
+    };
+
+    if let Err(er) = get_connection().and_then(|conn| WatchEvent::forget(&conn, &series.guid)) {
+        app.toast = Some(er.to_string());
+    }
+}
+
+pub(crate) fn undo_delete(app: &mut App) {
+    let Some(mut series) = app.data.last_deleted.take() else {
+        app.toast = Some("Nothing to undo".to_string());
+        return;
+    };
+
+    match get_connection().and_then(|conn| series.insert(&conn)) {
+        Ok(_) => {
+            app.toast = Some(format!("Restored \"{}\"", series.name));
+            app.data.total_series += 1;
+            app.data.ignore_cached_series = true;
+        }
+        Err(er) => {
+            app.toast = Some(er.to_string());
+            app.data.last_deleted = Some(series);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::{
+    app::App,
+    db::get_connection,
+    frame_lines,
+    history::WatchEvent,
+    input, main_key_handler, main_render,
+    series::Series,
+    term::{
+        attribute,
+        color::Color,
+        screen::{self, drawln},
+    },
+};
+
+const SCROLL_FOOTER: &str = "[j/k] scroll, [u] undo the latest, [q] back";
+
+pub(crate) fn start_history(app: &mut App) {
+    let Some(series) = app.data.available_series.get(app.data.hovered_series_idx) else {
+        return;
+    };
+
+    let guid = series.guid.clone();
+    let render_guid = guid.clone();
+    // How many of the newest events are scrolled past, shared so the renderer can clamp it
+    let skip = Arc::new(AtomicUsize::new(0));
+    let render_skip = skip.clone();
+
+    app.renderer = Arc::new(move |app| history_render(app, &render_guid, &render_skip));
+    app.keyboard_handler = Arc::new(move |app| history_key_handler(app, &guid, &skip));
+}
+
+fn history_render(app: &mut App, guid: &str, skip: &AtomicUsize) {
+    let loaded = get_connection().and_then(|conn| {
+        let series = Series::get_by_guid(&conn, guid)?;
+        let events = WatchEvent::for_series(&conn, guid)?;
+
+        Ok((series, events))
+    });
+
+    let (series, events) = match loaded {
+        Ok((Some(series), events)) => (series, events),
+        Ok((None, _)) => {
+            app.toast = Some("The series is gone".to_string());
+            return;
+        }
+        Err(er) => {
+            app.toast = Some(er.to_string());
+            return;
+        }
+    };
+
+    let lines: Vec<String> = events
+        .iter()
+        .enumerate()
+        .map(|(idx, event)| {
+            let change = event.new_episode - event.old_episode;
+            let (sign, color) = match change > 0 {
+                true => ("+", Color::Green),
+                false => ("", Color::Red),
+            };
+
+            format!(
+                "{: >3}  {}  {: >4} -> {: <4} {}{sign}{change}{}",
+                idx + 1,
+                event.watched_at,
+                event.old_episode,
+                event.new_episode,
+                color,
+                attribute::reset(),
+            )
+        })
+        .collect();
+
+    let title = format!(
+        "History: {} ({}/{})",
+        series.name, series.current_episode, series.total_episodes
+    );
+
+    // The title and the blank line under it, the blank line and footer under the events
+    let cols = app.term_size.cols as usize;
+    let longest_title = format!("{title}, events {0}-{0} of {0}", lines.len());
+    let reserved = screen::height(&longest_title, cols) + 2 + screen::height(SCROLL_FOOTER, cols);
+    let rows = (app.term_size.rows as usize)
+        .saturating_sub(reserved + frame_lines(app))
+        .max(1);
+
+    // Never past the point where the last page is full
+    let mut used = 0;
+    let max_skip = lines
+        .iter()
+        .rposition(|line| {
+            used += screen::height(line, cols);
+            used > rows
+        })
+        .map_or(0, |idx| idx + 1);
+    let from = skip.load(Ordering::Relaxed).min(max_skip);
+    skip.store(from, Ordering::Relaxed);
+
+    let shown = screen::fitting(&lines[from..], rows, cols).max(1);
+    let is_cut = shown < lines.len();
+
+    match is_cut {
+        true => drawln!(
+            "{title}, events {}-{} of {}",
+            from + 1,
+            from + shown,
+            lines.len()
+        ),
+        false => drawln!("{title}"),
+    }
+    drawln!();
+
+    if events.is_empty() {
+        drawln!("Nothing watched yet");
+        return;
+    }
+
+    for line in lines.iter().skip(from).take(shown) {
+        drawln!("{line}");
+    }
+
+    drawln!();
+    match is_cut {
+        true => drawln!("{SCROLL_FOOTER}"),
+        false => drawln!("[u] undo the latest, [q] back"),
+    }
+}
+
+fn history_key_handler(app: &mut App, guid: &str, skip: &AtomicUsize) {
+    app.should_render = true;
+
+    match input::get().trim() {
+        "q" | "<Esc>" => back_to_list(app),
+        "u" => rollback(app, guid),
+        // The renderer stops it at the last page
+        "j" | "<Down>" => {
+            skip.fetch_add(1, Ordering::Relaxed);
+        }
+        "k" | "<Up>" => {
+            let _ = skip.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |skip| {
+                skip.checked_sub(1)
+            });
+        }
+        _ => (),
+    }
+}
+
+// Not `start_listing`, the cursor stays on the series we came from
+fn back_to_list(app: &mut App) {
+    app.keyboard_handler = Arc::new(main_key_handler);
+    app.renderer = Arc::new(main_render);
+}
+
+fn rollback(app: &mut App, guid: &str) {
+    let rolled_back =
+        get_connection().and_then(|mut conn| WatchEvent::rollback_last(&mut conn, guid));
+
+    match rolled_back {
+        Ok(Some(event)) => {
+            app.toast = Some(format!(
+                "Back to episode {} from {}",
+                event.old_episode, event.new_episode
+            ));
+            app.data.ignore_cached_series = true;
+        }
+        Ok(None) => app.toast = Some("Nothing to undo".to_string()),
+        Err(er) => app.toast = Some(er.to_string()),
+    }
+}
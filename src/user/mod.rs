@@ -1,9 +1,27 @@
-pub(crate) mod create_series;
 pub(crate) mod create_input;
+pub(crate) mod create_series;
+pub(crate) mod delete_series;
+pub(crate) mod history_series;
+pub(crate) mod search_series;
+pub(crate) mod series_details;
+pub(crate) mod sort_series;
+pub(crate) mod stats_dashboard;
+pub(crate) mod status_filter;
+pub(crate) mod tag_filter;
+pub(crate) mod watch_series;
 
 #[derive(PartialEq)]
 pub(crate) enum InputType {
     Number,
     String,
     Boolean,
+    Status,
+    /// 1 to 10, or empty for no rating
+    Rating,
+    /// Free text that may span lines
+    Text,
+    /// Comma-separated
+    Tags,
+    /// Watched and total episodes per season, like "12/12, 5/24"
+    Seasons,
 }
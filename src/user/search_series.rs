@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use crate::{
+    app::App,
+    input, main_key_handler,
+    term::{key::Key, screen},
+};
+
+pub(crate) fn start_searching(app: &mut App) {
+    let previous = app.data.query.search.take();
+    app.data.query.search = Some(String::new());
+    app.keyboard_handler = Arc::new(move |app| search_key_handler(app, previous.clone()));
+
+    apply_search(app);
+}
+
+/// Filters the list on every key press, Enter keeps the filter and Esc puts back the previous one.
+/// `n`/`N` are letters of the search here and watch next/previous outside of it, so the jumps
+/// between matches live on Ctrl-N/Ctrl-P and the arrows instead.
+fn search_key_handler(app: &mut App, previous: Option<String>) {
+    app.should_render = true;
+
+    let mut search = app.data.query.search.clone().unwrap_or_default();
+    let _cursor = screen::show_cursor();
+
+    let Some(key) = input::get_key(&format!("/{search}")) else {
+        // Without a terminal there is nothing to be incremental about, take the whole line
+        let search = input::get_line("").unwrap_or_default();
+        finish_search(app, Some(search));
+        return;
+    };
+
+    match key {
+        Key::Enter => finish_search(app, Some(search)),
+        Key::Esc => finish_search(app, previous),
+        Key::Backspace if search.is_empty() => finish_search(app, previous),
+        Key::Backspace => {
+            search.pop();
+            app.data.query.search = Some(search);
+            apply_search(app);
+        }
+        Key::Ctrl('n') | Key::Down => jump_to_match(app, true),
+        Key::Ctrl('p') | Key::Up => jump_to_match(app, false),
+        Key::Ctrl('u') => {
+            app.data.query.search = Some(String::new());
+            apply_search(app);
+        }
+        Key::Char(c) => {
+            search.push(c);
+            app.data.query.search = Some(search);
+            apply_search(app);
+        }
+        _ => (),
+    }
+}
+
+fn finish_search(app: &mut App, search: Option<String>) {
+    app.keyboard_handler = Arc::new(main_key_handler);
+
+    // Enter keeps the search as typed, and the cursor where the jumps left it
+    let search = search.filter(|search| !search.is_empty());
+    if search != app.data.query.search {
+        app.data.query.search = search;
+        apply_search(app);
+    }
+}
+
+fn apply_search(app: &mut App) {
+    app.data.reset_cursor();
+}
+
+/// Steps through the matches, wrapping around at either end
+fn jump_to_match(app: &mut App, forward: bool) {
+    let total = app.data.total_series;
+    if total == 0 {
+        return;
+    }
+
+    let current = app.data.cursor();
+    let next = match forward {
+        true => (current + 1) % total,
+        false => (current + total - 1) % total,
+    };
+
+    app.data.jump_to(next);
+}
+
+pub(crate) fn clear_search(app: &mut App) {
+    if app.data.query.search.take().is_some() {
+        apply_search(app);
+    }
+}
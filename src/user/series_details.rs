@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use crate::{
+    app::App,
+    db::get_connection,
+    frame_lines,
+    history::WatchEvent,
+    input,
+    keybinds::nav::start_editing,
+    main_key_handler, main_render,
+    series::Series,
+    term::{
+        attribute,
+        color::Color,
+        screen::{self, drawln},
+    },
+    user::history_series::start_history,
+};
+
+const PROGRESS_WIDTH: usize = 30;
+const FOOTER: &str = "[e] edit, [H] history, [q] back";
+/// Older events are one `H` away
+const HISTORY_LINES: usize = 5;
+
+pub(crate) fn start_details(app: &mut App) {
+    let Some(series) = app.data.available_series.get(app.data.hovered_series_idx) else {
+        return;
+    };
+
+    let guid = series.guid.clone();
+    app.renderer = Arc::new(move |app| details_render(app, &guid));
+    app.keyboard_handler = Arc::new(details_key_handler);
+}
+
+fn details_render(app: &mut App, guid: &str) {
+    let loaded = get_connection().and_then(|conn| {
+        let Some(series) = Series::get_by_guid(&conn, guid)? else {
+            return Ok(None);
+        };
+        let events = WatchEvent::for_series(&conn, guid)?;
+
+        Ok(Some((series, events)))
+    });
+
+    let (series, events) = match loaded {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => {
+            app.toast = Some("The series is gone".to_string());
+            return;
+        }
+        Err(er) => {
+            app.toast = Some(er.to_string());
+            return;
+        }
+    };
+
+    let mut lines = vec![
+        format!(
+            "{}{}{}",
+            attribute::underline(),
+            series.name,
+            attribute::reset()
+        ),
+        String::new(),
+    ];
+
+    let airing = match series.is_airing_finished {
+        true => "finished airing",
+        false => "still airing",
+    };
+
+    let rows = [
+        ("Guid", series.guid.clone()),
+        (
+            "Progress",
+            progress_bar(series.current_episode, series.total_episodes),
+        ),
+        (
+            "Next",
+            series
+                .next_episode()
+                .unwrap_or_else(|| "all watched".to_string()),
+        ),
+        ("Status", format!("{}, {airing}", series.status.label())),
+        (
+            "Rating",
+            match series.rating {
+                Some(rating) => format!("{rating}/10"),
+                None => "-".to_string(),
+            },
+        ),
+        (
+            "Tags",
+            match series.tags.is_empty() {
+                true => "-".to_string(),
+                false => series.tags.join(", "),
+            },
+        ),
+        (
+            "Added",
+            series.added_at.clone().unwrap_or_else(|| "-".to_string()),
+        ),
+        (
+            "Updated",
+            series.updated_at.clone().unwrap_or_else(|| "-".to_string()),
+        ),
+    ];
+
+    for (label, value) in rows {
+        lines.push(format!(
+            "{}{label: >8}{}: {value}",
+            Color::Magenta,
+            attribute::reset()
+        ));
+    }
+
+    for (idx, season) in series.seasons.iter().enumerate() {
+        lines.push(format!(
+            "{}{: >8}{}: {}",
+            Color::Magenta,
+            format!("S{:02}", idx + 1),
+            attribute::reset(),
+            progress_bar(season.current_episode, season.total_episodes)
+        ));
+    }
+    lines.push(String::new());
+    lines.push(format!(
+        "{}Notes{}",
+        attribute::underline(),
+        attribute::reset()
+    ));
+    match series.notes.is_empty() {
+        true => lines.push("-".to_string()),
+        false => lines.extend(series.notes.lines().map(str::to_string)),
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+        "{}History{}",
+        attribute::underline(),
+        attribute::reset()
+    ));
+
+    if events.is_empty() {
+        lines.push("Nothing watched yet".to_string());
+    }
+
+    for event in events.iter().take(HISTORY_LINES) {
+        lines.push(format!(
+            "{}  {} -> {}",
+            event.watched_at, event.old_episode, event.new_episode
+        ));
+    }
+
+    if events.len() > HISTORY_LINES {
+        lines.push(format!(
+            "... {} more, [H] for all",
+            events.len() - HISTORY_LINES
+        ));
+    }
+
+    // The blank line and footer stay, long notes are cut short instead
+    let cols = app.term_size.cols as usize;
+    let rows = (app.term_size.rows as usize)
+        .saturating_sub(1 + screen::height(FOOTER, cols) + frame_lines(app))
+        .max(1);
+
+    let mut shown = screen::fitting(&lines, rows, cols);
+    if shown < lines.len() {
+        // Room for the line saying so
+        shown = shown.saturating_sub(1);
+    }
+
+    for line in &lines[..shown] {
+        drawln!("{line}");
+    }
+
+    if shown < lines.len() {
+        drawln!("... {} more lines cut to fit", lines.len() - shown);
+    }
+
+    drawln!();
+    drawln!("{FOOTER}");
+}
+
+fn progress_bar(current_episode: i32, total_episodes: i32) -> String {
+    if total_episodes <= 0 {
+        return format!("{current_episode} episodes, total unknown");
+    }
+
+    let ratio = (current_episode as f64 / total_episodes as f64).clamp(0.0, 1.0);
+    let filled = (ratio * PROGRESS_WIDTH as f64).round() as usize;
+
+    format!(
+        "{}{}{}{} {current_episode}/{total_episodes} ({:.0}%)",
+        Color::Green,
+        "█".repeat(filled),
+        attribute::reset(),
+        "░".repeat(PROGRESS_WIDTH - filled),
+        ratio * 100.0
+    )
+}
+
+fn details_key_handler(app: &mut App) {
+    app.should_render = true;
+
+    match input::get().trim() {
+        "q" | "<Esc>" => {
+            app.keyboard_handler = Arc::new(main_key_handler);
+            app.renderer = Arc::new(main_render);
+        }
+        // Both leave for the list afterwards, the hovered series is still the one shown here
+        "e" => start_editing(app),
+        "H" => start_history(app),
+        _ => (),
+    }
+}
@@ -0,0 +1,23 @@
+use crate::{app::App, db::get_connection};
+
+pub(crate) fn cycle_sort_key(app: &mut App) {
+    let sort = &mut app.data.query.sort;
+    sort.key = sort.key.next();
+
+    apply_sort(app);
+}
+
+pub(crate) fn toggle_sort_direction(app: &mut App) {
+    let sort = &mut app.data.query.sort;
+    sort.descending = !sort.descending;
+
+    apply_sort(app);
+}
+
+fn apply_sort(app: &mut App) {
+    app.data.reset_cursor();
+
+    if let Err(er) = get_connection().and_then(|conn| app.data.query.sort.save(&conn)) {
+        app.toast = Some(format!("Could not remember the sort: {er}"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use crate::{
+    app::App,
+    db::get_connection,
+    input, main_key_handler, main_render,
+    stats::{Stats, WEEKS},
+    term::{attribute, color::Color, screen::drawln},
+};
+
+const BAR: char = '█';
+
+pub(crate) fn start_stats(app: &mut App) {
+    app.renderer = Arc::new(stats_render);
+    app.keyboard_handler = Arc::new(stats_key_handler);
+}
+
+pub(crate) fn stats_render(app: &mut App) {
+    let stats = match get_connection().and_then(|conn| Stats::load(&conn)) {
+        Ok(stats) => stats,
+        Err(er) => {
+            app.toast = Some(er.to_string());
+            return;
+        }
+    };
+
+    drawln!("{}Statistics{}", attribute::underline(), attribute::reset());
+    drawln!();
+
+    let average = match stats.average_completion {
+        Some(average) => format!("{average:.1}%"),
+        None => "-".to_string(),
+    };
+
+    let rows = [
+        ("Series", stats.total_series.to_string(), Color::Blue),
+        ("Finished", stats.finished.to_string(), Color::Green),
+        ("In progress", stats.in_progress.to_string(), Color::Magenta),
+        (
+            "Episodes watched",
+            stats.episodes_watched.to_string(),
+            Color::Green,
+        ),
+        ("Average completion", average, Color::Blue),
+    ];
+
+    for (label, value, color) in rows {
+        drawln!("{label: >18}: {color}{value}{}", attribute::reset());
+    }
+
+    drawln!();
+    drawln!(
+        "{}Episodes per week{}, last {WEEKS}",
+        attribute::underline(),
+        attribute::reset()
+    );
+    drawln!();
+
+    if !stats.has_history {
+        drawln!("Nothing yet, the chart fills up as you watch");
+        return;
+    }
+
+    // "2024-01-01 | " before the bar and the count after it
+    let width = (app.term_size.cols as usize).saturating_sub(20).max(10);
+    let most = stats
+        .weekly
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for (week, count) in &stats.weekly {
+        let length = (*count * width as i64 / most) as usize;
+        let bar = BAR.to_string().repeat(length);
+
+        drawln!(
+            "{week} | {}{bar}{} {count}",
+            Color::Green,
+            attribute::reset()
+        );
+    }
+}
+
+pub(crate) fn stats_key_handler(app: &mut App) {
+    app.should_render = true;
+
+    match input::get().trim() {
+        "q" | "<Esc>" => {
+            app.keyboard_handler = Arc::new(main_key_handler);
+            app.renderer = Arc::new(main_render);
+        }
+        _ => (),
+    }
+}
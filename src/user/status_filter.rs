@@ -0,0 +1,35 @@
+use crate::{app::App, series::WatchStatus};
+
+pub(crate) fn next_status_view(app: &mut App) {
+    let views = views();
+    let idx = current_view_idx(app);
+
+    switch_view(app, views[(idx + 1) % views.len()]);
+}
+
+pub(crate) fn previous_status_view(app: &mut App) {
+    let views = views();
+    let idx = current_view_idx(app);
+
+    switch_view(app, views[(idx + views.len() - 1) % views.len()]);
+}
+
+// `None` is the view with every series in it
+fn views() -> Vec<Option<WatchStatus>> {
+    let mut views = vec![None];
+    views.extend(WatchStatus::ALL.map(Some));
+
+    views
+}
+
+fn current_view_idx(app: &App) -> usize {
+    views()
+        .iter()
+        .position(|view| *view == app.data.query.status)
+        .unwrap_or(0)
+}
+
+fn switch_view(app: &mut App, status: Option<WatchStatus>) {
+    app.data.query.status = status;
+    app.data.reset_cursor();
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use crate::{app::App, input, main_key_handler, series::parse_tags};
+
+pub(crate) fn start_tag_filter(app: &mut App) {
+    app.toast = Some(format!(
+        "Filter by tags, comma separated, empty shows everything (matching {})",
+        app.data.query.tag_match.label()
+    ));
+    app.keyboard_handler = Arc::new(tag_filter_key_handler);
+}
+
+fn tag_filter_key_handler(app: &mut App) {
+    app.should_render = true;
+    app.keyboard_handler = Arc::new(main_key_handler);
+
+    let Some(tags) = input::get_line(&app.data.query.tags.join(", ")) else {
+        return;
+    };
+
+    app.data.query.tags = parse_tags(&tags);
+    apply_tag_filter(app);
+}
+
+pub(crate) fn toggle_tag_match(app: &mut App) {
+    app.data.query.tag_match = app.data.query.tag_match.toggle();
+
+    if !app.data.query.tags.is_empty() {
+        apply_tag_filter(app);
+    }
+}
+
+fn apply_tag_filter(app: &mut App) {
+    app.data.reset_cursor();
+}
@@ -0,0 +1,38 @@
+use crate::{app::App, db::get_connection, keybinds::UserInput};
+
+pub(crate) fn watch_next(app: &mut App, input: &UserInput) {
+    add_episodes(app, count(input));
+}
+
+pub(crate) fn watch_previous(app: &mut App, input: &UserInput) {
+    add_episodes(app, -count(input));
+}
+
+/// The count typed before the key, huge ones are cut to the total by `Series::add_episodes`
+fn count(input: &UserInput) -> i32 {
+    i32::try_from(input.digits_prefix.unwrap_or(1)).unwrap_or(i32::MAX)
+}
+
+fn add_episodes(app: &mut App, qty: i32) {
+    let Some(series) = app.data.available_series.get(app.data.hovered_series_idx) else {
+        return;
+    };
+
+    let mut series = series.clone();
+
+    match get_connection().and_then(|conn| series.try_watch(&conn, qty)) {
+        Ok(_) => {
+            let finished = if series.is_finished {
+                ", finished!"
+            } else {
+                ""
+            };
+            app.toast = Some(format!(
+                "{}: episode {}/{}{}",
+                series.name, series.current_episode, series.total_episodes, finished
+            ));
+            app.data.ignore_cached_series = true;
+        }
+        Err(er) => app.toast = Some(er.to_string()),
+    }
+}